@@ -1 +1,312 @@
-fn main() {}
+use libsponge::{
+    EthernetAddress, EthernetFrame, IPv4NUM, InternetDatagram, NetworkInterface, Router,
+};
+
+use std::{net::Ipv4Addr, process::ExitCode};
+
+const INITIAL_TTL: u8 = 64;
+const MAX_ROUNDS: usize = 64;
+
+fn ip(addr: &str) -> u32 {
+    addr.parse::<Ipv4Addr>()
+        .expect("topology uses valid IPv4 addresses")
+        .into()
+}
+
+// derive a unique, locally administered MAC from the interface's IP address
+fn ethernet_address(ip: u32) -> EthernetAddress {
+    let [a, b, c, d] = ip.to_be_bytes();
+    EthernetAddress([0x02, 0, a, b, c, d])
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Delivery {
+    src: u32,
+    ttl: u8,
+    payload: Vec<u8>,
+}
+
+struct Host {
+    name: &'static str,
+    ip: u32,
+    gateway: u32,
+    router: usize,
+    interface: NetworkInterface,
+    received: Vec<Delivery>,
+    expected: Vec<Delivery>,
+}
+
+impl Host {
+    fn new(name: &'static str, ip: u32, gateway: u32, router: usize) -> Self {
+        Self {
+            name,
+            ip,
+            gateway,
+            router,
+            interface: NetworkInterface::new(ethernet_address(ip), IPv4NUM(ip).into()),
+            received: Vec::new(),
+            expected: Vec::new(),
+        }
+    }
+
+    fn send_to(&mut self, dst: u32, ttl: u8, payload: Vec<u8>) {
+        let mut dgram = InternetDatagram::default();
+        dgram.header_mut().src = self.ip;
+        dgram.header_mut().dst = dst;
+        dgram.header_mut().ttl = ttl;
+        dgram.header_mut().len = 20 + payload.len() as u16;
+        *dgram.payload_mut() = payload.into();
+        self.interface
+            .send_datagram(&dgram, &IPv4NUM(self.gateway).into());
+    }
+
+    fn recv_frame(&mut self, frame: &EthernetFrame) {
+        if let Some(dgram) = self.interface.recv_frame(frame) {
+            self.received.push(Delivery {
+                src: dgram.header().src,
+                ttl: dgram.header().ttl,
+                payload: dgram.payload().into(),
+            });
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+enum Endpoint {
+    Host(usize),
+    Router(usize, usize),
+}
+
+// an Ethernet segment: every frame sent by one endpoint reaches all the others
+struct Link {
+    endpoints: Vec<Endpoint>,
+}
+
+struct Network {
+    hosts: Vec<Host>,
+    routers: Vec<Router>,
+    links: Vec<Link>,
+}
+
+impl Network {
+    fn add_router_interface(&mut self, router: usize, addr: &str) -> usize {
+        let addr = ip(addr);
+        let interface = NetworkInterface::new(ethernet_address(addr), IPv4NUM(addr).into());
+        self.routers[router].add_interface(interface.into())
+    }
+
+    fn add_host(&mut self, name: &'static str, addr: &str, gateway: &str, router: usize) -> usize {
+        self.hosts
+            .push(Host::new(name, ip(addr), ip(gateway), router));
+        self.hosts.len() - 1
+    }
+
+    //            applesauce  applecrisp          cherrypie
+    //                 \          /                    |
+    //                  [router 0] ------------- [router 1]
+    //                      |                    /       \
+    //                   dm42/dm43           hs4         mit5
+    fn new() -> Self {
+        let mut network = Self {
+            hosts: Vec::new(),
+            routers: vec![Router::new(), Router::new()],
+            links: Vec::new(),
+        };
+
+        let r0_apple = network.add_router_interface(0, "10.0.0.1");
+        let r0_uplink = network.add_router_interface(0, "172.16.0.1");
+        let r0_dm = network.add_router_interface(0, "198.178.229.1");
+        let r1_uplink = network.add_router_interface(1, "172.16.0.2");
+        let r1_cherry = network.add_router_interface(1, "192.168.0.1");
+        let r1_hs = network.add_router_interface(1, "143.195.0.1");
+        let r1_mit = network.add_router_interface(1, "128.30.76.1");
+
+        let applesauce = network.add_host("applesauce", "10.0.0.2", "10.0.0.1", 0);
+        let applecrisp = network.add_host("applecrisp", "10.0.0.3", "10.0.0.1", 0);
+        let dm42 = network.add_host("dm42", "198.178.229.42", "198.178.229.1", 0);
+        let dm43 = network.add_host("dm43", "198.178.229.43", "198.178.229.1", 0);
+        let cherrypie = network.add_host("cherrypie", "192.168.0.2", "192.168.0.1", 1);
+        let hs4 = network.add_host("hs4", "143.195.0.2", "143.195.0.1", 1);
+        let mit5 = network.add_host("mit5", "128.30.76.255", "128.30.76.1", 1);
+
+        let r0 = &mut network.routers[0];
+        r0.add_route(ip("10.0.0.0"), 8, None, r0_apple);
+        r0.add_route(ip("172.16.0.0"), 16, None, r0_uplink);
+        r0.add_route(ip("198.178.229.0"), 24, None, r0_dm);
+        r0.add_route(
+            ip("0.0.0.0"),
+            0,
+            Some(IPv4NUM(ip("172.16.0.2")).into()),
+            r0_uplink,
+        );
+
+        let r1 = &mut network.routers[1];
+        r1.add_route(ip("172.16.0.0"), 16, None, r1_uplink);
+        r1.add_route(ip("192.168.0.0"), 24, None, r1_cherry);
+        r1.add_route(ip("143.195.0.0"), 16, None, r1_hs);
+        r1.add_route(ip("128.30.76.0"), 24, None, r1_mit);
+        r1.add_route(
+            ip("10.0.0.0"),
+            8,
+            Some(IPv4NUM(ip("172.16.0.1")).into()),
+            r1_uplink,
+        );
+        r1.add_route(
+            ip("198.178.229.0"),
+            24,
+            Some(IPv4NUM(ip("172.16.0.1")).into()),
+            r1_uplink,
+        );
+
+        let links = [
+            vec![
+                Endpoint::Host(applesauce),
+                Endpoint::Host(applecrisp),
+                Endpoint::Router(0, r0_apple),
+            ],
+            vec![
+                Endpoint::Router(0, r0_uplink),
+                Endpoint::Router(1, r1_uplink),
+            ],
+            vec![
+                Endpoint::Host(dm42),
+                Endpoint::Host(dm43),
+                Endpoint::Router(0, r0_dm),
+            ],
+            vec![Endpoint::Host(cherrypie), Endpoint::Router(1, r1_cherry)],
+            vec![Endpoint::Host(hs4), Endpoint::Router(1, r1_hs)],
+            vec![Endpoint::Host(mit5), Endpoint::Router(1, r1_mit)],
+        ];
+        network.links = links
+            .into_iter()
+            .map(|endpoints| Link { endpoints })
+            .collect();
+        network
+    }
+
+    fn take_frames(&mut self, endpoint: Endpoint) -> Vec<EthernetFrame> {
+        let frames = match endpoint {
+            Endpoint::Host(h) => self.hosts[h].interface.frames_out_mut(),
+            Endpoint::Router(r, n) => self.routers[r].interface_mut(n).frames_out_mut(),
+        };
+        frames.drain(..).collect()
+    }
+
+    fn deliver(&mut self, endpoint: Endpoint, frame: &EthernetFrame) {
+        match endpoint {
+            Endpoint::Host(h) => self.hosts[h].recv_frame(frame),
+            Endpoint::Router(r, n) => self.routers[r].interface_mut(n).recv_frame(frame),
+        }
+    }
+
+    // move frames across links and let routers forward until the network is quiet
+    fn simulate(&mut self) {
+        for _ in 0..MAX_ROUNDS {
+            let mut moved = false;
+            for l in 0..self.links.len() {
+                let endpoints = self.links[l].endpoints.clone();
+                for (i, &from) in endpoints.iter().enumerate() {
+                    for frame in self.take_frames(from) {
+                        moved = true;
+                        endpoints
+                            .iter()
+                            .enumerate()
+                            .filter(|&(j, _)| j != i)
+                            .for_each(|(_, &to)| self.deliver(to, &frame));
+                    }
+                }
+            }
+            self.routers.iter_mut().for_each(Router::route);
+            if !moved {
+                return;
+            }
+        }
+    }
+
+    fn send(&mut self, src: usize, dst: usize, ttl: u8) {
+        let payload = format!(
+            "{} -> {} (ttl {})",
+            self.hosts[src].name, self.hosts[dst].name, ttl
+        )
+        .into_bytes();
+        let hops = if self.hosts[src].router == self.hosts[dst].router {
+            1
+        } else {
+            2
+        };
+        if ttl > hops {
+            let expected = Delivery {
+                src: self.hosts[src].ip,
+                ttl: ttl - hops,
+                payload: payload.clone(),
+            };
+            self.hosts[dst].expected.push(expected);
+        }
+        let dst_ip = self.hosts[dst].ip;
+        self.hosts[src].send_to(dst_ip, ttl, payload);
+    }
+
+    fn check(&mut self) -> bool {
+        let mut ok = true;
+        for host in &mut self.hosts {
+            let key = |d: &Delivery| (d.src, d.payload.clone());
+            host.received.sort_by_key(key);
+            host.expected.sort_by_key(key);
+            if host.received != host.expected {
+                ok = false;
+                eprintln!(
+                    "{}: expected {} datagram(s), got {}",
+                    host.name,
+                    host.expected.len(),
+                    host.received.len()
+                );
+                for delivery in host.received.iter().filter(|d| !host.expected.contains(d)) {
+                    eprintln!(
+                        "  unexpected: {} (ttl {})",
+                        String::from_utf8_lossy(&delivery.payload),
+                        delivery.ttl
+                    );
+                }
+                for delivery in host.expected.iter().filter(|d| !host.received.contains(d)) {
+                    eprintln!(
+                        "  missing: {} (ttl {})",
+                        String::from_utf8_lossy(&delivery.payload),
+                        delivery.ttl
+                    );
+                }
+            }
+            host.received.clear();
+            host.expected.clear();
+        }
+        ok
+    }
+}
+
+fn main() -> ExitCode {
+    let mut network = Network::new();
+    let n = network.hosts.len();
+    let mut ok = true;
+
+    // every host talks to every other host, then datagrams whose TTL runs out on the way
+    for ttl in [INITIAL_TTL, 2, 1] {
+        for src in 0..n {
+            for dst in (0..n).filter(|&dst| dst != src) {
+                network.send(src, dst, ttl);
+            }
+        }
+        network.simulate();
+        let round_ok = network.check();
+        println!(
+            "all-pairs exchange with ttl {}: {}",
+            ttl,
+            if round_ok { "ok" } else { "FAILED" }
+        );
+        ok &= round_ok;
+    }
+
+    if !ok {
+        return ExitCode::FAILURE;
+    }
+    println!("\ncongratulations! all datagrams were routed successfully.");
+    ExitCode::SUCCESS
+}
@@ -1 +1,148 @@
-fn main() {}
+use libsponge::{CongestionControlKind, TCPConfig, TCPConnection, WrappingU32};
+
+use anyhow::{Result, anyhow, bail};
+
+use std::{
+    env,
+    process::ExitCode,
+    time::{Duration, Instant},
+};
+
+const DEFAULT_LEN: usize = 100 * 1024 * 1024;
+const USAGE: &str = "Usage: tcp_benchmark [bytes]";
+const ISN: u32 = 1;
+// simulated time per round, far below any retransmission timeout
+const TICK_MS: u64 = 1;
+
+struct Direction {
+    name: &'static str,
+    to_send: Vec<u8>,
+    sent: usize,
+    closed: bool,
+    received: Vec<u8>,
+    segments: usize,
+    // the furthest the sender has reached, and how often it went back
+    next_seqno: u64,
+    retransmissions: usize,
+    elapsed: Option<Duration>,
+}
+
+impl Direction {
+    fn new(name: &'static str, len: usize, seed: u8) -> Self {
+        Self {
+            name,
+            to_send: (0..len)
+                .map(|i| (i as u8).wrapping_mul(31) ^ seed)
+                .collect(),
+            sent: 0,
+            closed: false,
+            received: Vec::with_capacity(len),
+            segments: 0,
+            next_seqno: 0,
+            retransmissions: 0,
+            elapsed: None,
+        }
+    }
+
+    // write as much as the sender accepts, then close its outbound stream once everything is in
+    fn write_into(&mut self, sender: &mut TCPConnection) {
+        while self.sent < self.to_send.len() && sender.remaining_outbound_capacity() > 0 {
+            self.sent += sender.write(&self.to_send[self.sent..]);
+        }
+        if self.sent == self.to_send.len() && !self.closed {
+            sender.end_input_stream();
+            self.closed = true;
+        }
+    }
+
+    fn move_segments(&mut self, from: &mut TCPConnection, to: &mut TCPConnection) {
+        let isn = WrappingU32::new(ISN);
+        while let Some(seg) = from.segments_out_mut().pop_front() {
+            let len = seg.length_in_sequence_space() as u64;
+            if len > 0 {
+                let seqno = WrappingU32::unwrap(&seg.header().seq_no, &isn, self.next_seqno);
+                if seqno < self.next_seqno {
+                    self.retransmissions += 1;
+                }
+                self.next_seqno = self.next_seqno.max(seqno + len);
+            }
+            to.segment_received(&seg);
+            self.segments += 1;
+        }
+    }
+
+    fn read_from(&mut self, receiver: &mut TCPConnection, start: Instant) {
+        let inbound = receiver.inbound_stream_mut();
+        let available = inbound.buffer_size();
+        if available > 0 {
+            self.received.extend(inbound.read(available));
+        }
+        if inbound.eof() && self.elapsed.is_none() {
+            self.elapsed = Some(start.elapsed());
+        }
+    }
+
+    fn report(&self) -> Result<()> {
+        if self.received != self.to_send {
+            bail!("{}: received data does not match what was sent", self.name);
+        }
+        let elapsed = self
+            .elapsed
+            .ok_or_else(|| anyhow!("{}: stream never finished", self.name))?;
+        let gigabits_per_second = self.to_send.len() as f64 * 8.0 / elapsed.as_nanos() as f64;
+        println!(
+            "CPU-limited throughput {}: {:.2} Gbit/s ({} segments, {} retransmitted)",
+            self.name, gigabits_per_second, self.segments, self.retransmissions
+        );
+        Ok(())
+    }
+}
+
+fn main_loop(len: usize) -> Result<()> {
+    let cfg = TCPConfig {
+        fixed_isn: Some(WrappingU32::new(ISN)),
+        // nothing is lost in memory, so only the receiver's window should limit the sender
+        congestion_control: CongestionControlKind::None,
+        ..Default::default()
+    };
+    let mut x = TCPConnection::with_config(&cfg);
+    let mut y = TCPConnection::with_config(&cfg);
+    let mut x_to_y = Direction::new("x -> y", len, 0x5a);
+    let mut y_to_x = Direction::new("y -> x", len, 0xa5);
+
+    let start = Instant::now();
+    x.connect();
+    while x.active() || y.active() {
+        x_to_y.write_into(&mut x);
+        y_to_x.write_into(&mut y);
+
+        x_to_y.move_segments(&mut x, &mut y);
+        y_to_x.move_segments(&mut y, &mut x);
+
+        x_to_y.read_from(&mut y, start);
+        y_to_x.read_from(&mut x, start);
+
+        // time passes
+        x.tick(TICK_MS.into());
+        y.tick(TICK_MS.into());
+    }
+
+    x_to_y.report()?;
+    y_to_x.report()
+}
+
+fn main() -> ExitCode {
+    let len = match env::args().nth(1).map(|arg| arg.parse::<usize>()) {
+        None => DEFAULT_LEN,
+        Some(Ok(len)) => len,
+        Some(Err(err)) => {
+            eprintln!("Error: {}\n{}", err, USAGE);
+            return ExitCode::FAILURE;
+        }
+    };
+    if let Err(err) = main_loop(len) {
+        eprintln!("Error: {}", err);
+        return ExitCode::FAILURE;
+    }
+    ExitCode::SUCCESS
+}
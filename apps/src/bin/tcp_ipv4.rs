@@ -0,0 +1,123 @@
+use libsponge::{
+    Address, FDAdapterConfig, LossyFDAdaptor, LossyTCPOverIPv4SpongeSocket, TCPConfig,
+    TCPOverIPv4OverTunFdAdapter, TunFD, bidirectional_stream_copy, random_port,
+};
+
+use anyhow::{Result, anyhow, bail};
+
+use std::{env, process::ExitCode};
+
+const USAGE: &str = "Usage: tcp_ipv4 [options] <host> <port>
+
+   -l              Server (listen) mode.                 (client mode if not set)
+
+   -a <addr>       Set source address (client mode only) [default: 169.254.144.9]
+   -s <port>       Set source port (client mode only)    [default: random]
+
+   -w <winsz>      Use a window of <winsz> bytes         [default: 64000]
+   -t <tmout>      Set rt_timeout to <tmout>             [default: 1000]
+
+   -d <tundev>     Connect to tun <tundev>               [default: tun144]
+
+   -Lu <rate>      Set uplink loss to <rate> (0..1)      [default: 0]
+   -Ld <rate>      Set downlink loss to <rate> (0..1)    [default: 0]
+
+   -h              Show this message and quit.";
+
+struct Config {
+    c_tcp: TCPConfig,
+    c_ad: FDAdapterConfig,
+    tun_dev: String,
+    listen: bool,
+}
+
+fn get_config() -> Result<Option<Config>> {
+    let mut c_tcp = TCPConfig::default();
+    let mut c_ad = FDAdapterConfig::default();
+    let mut tun_dev = String::from("tun144");
+    let mut listen = false;
+    let mut source_address = String::from("169.254.144.9");
+    let mut source_port = random_port().to_string();
+    let mut positional = Vec::new();
+
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().ok_or_else(|| anyhow!("{} needs a value", arg));
+        match arg.as_str() {
+            "-l" => listen = true,
+            "-a" => source_address = value()?,
+            "-s" => source_port = value()?,
+            "-w" => {
+                let window = value()?;
+                c_tcp.recv_capacity = window
+                    .parse()
+                    .map_err(|_| anyhow!("invalid window size: {}", window))?;
+            }
+            "-t" => {
+                let timeout = value()?;
+                c_tcp.rt_timeout = timeout
+                    .parse()
+                    .map_err(|_| anyhow!("invalid rt_timeout: {}", timeout))?;
+            }
+            "-d" => tun_dev = value()?,
+            "-Lu" => c_ad.loss_rate_up = FDAdapterConfig::parse_loss_rate(&value()?)?,
+            "-Ld" => c_ad.loss_rate_dn = FDAdapterConfig::parse_loss_rate(&value()?)?,
+            "-h" => return Ok(None),
+            _ if arg.starts_with('-') => bail!("unknown option: {}", arg),
+            _ => positional.push(arg),
+        }
+    }
+
+    let [host, port]: [String; 2] = positional
+        .try_into()
+        .map_err(|_| anyhow!("expected a host and a port"))?;
+    if listen {
+        c_ad.source = Address::try_from_hostname(&host, &port)?;
+        if c_ad.source.port()? == 0 {
+            bail!("listen port cannot be zero in server mode");
+        }
+    } else {
+        c_ad.destination = Address::try_from_hostname(&host, &port)?;
+        c_ad.source = Address::try_from_string(&source_address, source_port.as_str())?;
+    }
+
+    Ok(Some(Config {
+        c_tcp,
+        c_ad,
+        tun_dev,
+        listen,
+    }))
+}
+
+fn run(cfg: Config) -> Result<()> {
+    let tun = TunFD::try_new(&cfg.tun_dev)?;
+    let adapter = LossyFDAdaptor::new(TCPOverIPv4OverTunFdAdapter::new(tun));
+    let mut tcp_socket = LossyTCPOverIPv4SpongeSocket::new(adapter)?;
+    if cfg.listen {
+        tcp_socket.listen_and_accept(&cfg.c_tcp, cfg.c_ad)?;
+    } else {
+        tcp_socket.connect(&cfg.c_tcp, cfg.c_ad)?;
+    }
+
+    bidirectional_stream_copy(&tcp_socket)?;
+    tcp_socket.wait_until_closed()
+}
+
+fn main() -> ExitCode {
+    let cfg = match get_config() {
+        Ok(Some(cfg)) => cfg,
+        Ok(None) => {
+            println!("{}", USAGE);
+            return ExitCode::SUCCESS;
+        }
+        Err(err) => {
+            eprintln!("Error: {}\n\n{}", err, USAGE);
+            return ExitCode::FAILURE;
+        }
+    };
+    if let Err(err) = run(cfg) {
+        eprintln!("Error: {}", err);
+        return ExitCode::FAILURE;
+    }
+    ExitCode::SUCCESS
+}
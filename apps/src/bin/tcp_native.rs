@@ -1 +1,98 @@
-fn main() {}
+use libsponge::{Address, NoneAdapter, TCPSocket, bidirectional_stream_copy};
+
+use anyhow::{Result, anyhow, bail};
+
+use std::{env, process::ExitCode};
+
+const USAGE: &str = "Usage: tcp_native [options] <host> <port>
+
+   -l              Server (listen) mode.                 (client mode if not set)
+
+   -a <addr>       Set source address (client mode only) [default: kernel's choice]
+   -s <port>       Set source port (client mode only)    [default: kernel's choice]
+
+   -h              Show this message and quit.";
+
+struct Config {
+    host: String,
+    port: String,
+    source_address: Option<String>,
+    source_port: Option<String>,
+    listen: bool,
+}
+
+fn get_config() -> Result<Option<Config>> {
+    let mut listen = false;
+    let mut source_address = None;
+    let mut source_port = None;
+    let mut positional = Vec::new();
+
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().ok_or_else(|| anyhow!("{} needs a value", arg));
+        match arg.as_str() {
+            "-l" => listen = true,
+            "-a" => source_address = Some(value()?),
+            "-s" => source_port = Some(value()?),
+            "-h" => return Ok(None),
+            _ if arg.starts_with('-') => bail!("unknown option: {}", arg),
+            _ => positional.push(arg),
+        }
+    }
+
+    let [host, port]: [String; 2] = positional
+        .try_into()
+        .map_err(|_| anyhow!("expected a host and a port"))?;
+    Ok(Some(Config {
+        host,
+        port,
+        source_address,
+        source_port,
+        listen,
+    }))
+}
+
+fn open_socket(cfg: &Config) -> Result<TCPSocket<NoneAdapter>> {
+    let address = Address::try_from_hostname(&cfg.host, &cfg.port)?;
+    let mut socket = TCPSocket::try_default()?;
+    if cfg.listen {
+        socket.set_reuseaddr()?;
+        socket.bind(&address)?;
+        socket.listen(None)?;
+        eprintln!("DEBUG: Listening for incoming connection...");
+        let connection = socket.accept()?;
+        eprintln!("New connection from {}.", connection.peer_addr()?);
+        return Ok(connection);
+    }
+
+    if cfg.source_address.is_some() || cfg.source_port.is_some() {
+        let source = Address::try_from_string(
+            cfg.source_address.as_deref().unwrap_or("0.0.0.0"),
+            cfg.source_port.as_deref().unwrap_or("0"),
+        )?;
+        socket.bind(&source)?;
+    }
+    eprintln!("DEBUG: Connecting to {}...", address);
+    socket.connect(&address)?;
+    eprintln!("Successfully connected to {}.", address);
+    Ok(socket)
+}
+
+fn main() -> ExitCode {
+    let cfg = match get_config() {
+        Ok(Some(cfg)) => cfg,
+        Ok(None) => {
+            println!("{}", USAGE);
+            return ExitCode::SUCCESS;
+        }
+        Err(err) => {
+            eprintln!("Error: {}\n\n{}", err, USAGE);
+            return ExitCode::FAILURE;
+        }
+    };
+    if let Err(err) = open_socket(&cfg).and_then(|socket| bidirectional_stream_copy(&socket)) {
+        eprintln!("Error: {}", err);
+        return ExitCode::FAILURE;
+    }
+    ExitCode::SUCCESS
+}
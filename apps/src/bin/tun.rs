@@ -1 +1,65 @@
-fn main() {}
+use libsponge::{
+    Buffer, IPv4Datagram, IPv4Header, NoneAdapter, NoneProtocol, ParseError, TCPSegment, TunFD,
+};
+
+use anyhow::{Result, bail};
+
+use std::{env, process::ExitCode};
+
+const USAGE: &str = "Usage: tun [tun_device]";
+const TUN_DFLT: &str = "tun144";
+
+fn report(what: &str, err: ParseError) {
+    println!("    ({} failed to parse: {:?}, {})", what, err, err);
+}
+
+fn dump(data: Vec<u8>) {
+    println!("** Got {} bytes", data.len());
+
+    let mut dgram = IPv4Datagram::default();
+    if let Err(err) = dgram.try_parse(Buffer::from(data)) {
+        return report("IPv4 datagram", err);
+    }
+    println!("    IPv4 header: {}", dgram.header());
+    if dgram.header().proto != IPv4Header::PROTO_TCP {
+        return;
+    }
+
+    let mut seg = TCPSegment::default();
+    let payload = Buffer::from(Into::<Vec<u8>>::into(dgram.payload()));
+    if let Err(err) = seg.parse(payload, dgram.header().pseudo_cksum()) {
+        return report("TCP segment", err);
+    }
+    for line in seg.header().to_string().lines() {
+        println!("    {}", line);
+    }
+    println!("    TCP payload: {} bytes", seg.payload().len());
+}
+
+fn run(dev_name: &str) -> Result<()> {
+    let mut tun = TunFD::<NoneAdapter, NoneProtocol>::try_new(dev_name)?;
+    eprintln!("reading datagrams from {}", dev_name);
+    loop {
+        let data = tun.read(None)?;
+        if tun.eof() {
+            bail!("{} was closed", dev_name);
+        }
+        dump(data);
+    }
+}
+
+fn main() -> ExitCode {
+    let dev_name = match env::args().skip(1).collect::<Vec<_>>().as_slice() {
+        [] => TUN_DFLT.to_string(),
+        [dev_name] if !dev_name.starts_with('-') => dev_name.clone(),
+        _ => {
+            eprintln!("{}", USAGE);
+            return ExitCode::FAILURE;
+        }
+    };
+    if let Err(err) = run(&dev_name) {
+        eprintln!("Error: {}", err);
+        return ExitCode::FAILURE;
+    }
+    ExitCode::SUCCESS
+}
@@ -1 +1,96 @@
-fn main() {}
+use libsponge::{Address, NetParser, NoneAdapter, ParseError, TCPSegment, UDPSocket, hexdump};
+
+use anyhow::{Result, anyhow, bail};
+
+use std::{env, process::ExitCode};
+
+const USAGE: &str = "Usage: udp_tcpdump [-x] [-K] <port>\n\
+                     \x20 -x  print a hexdump of each segment's payload\n\
+                     \x20 -K  don't verify TCP checksums";
+
+struct Args {
+    port: String,
+    hex: bool,
+    check_cksum: bool,
+}
+
+fn parse_args() -> Result<Args> {
+    let mut port = None;
+    let mut hex = false;
+    let mut check_cksum = true;
+
+    for arg in env::args().skip(1) {
+        match arg.as_str() {
+            "-x" => hex = true,
+            "-K" => check_cksum = false,
+            _ if arg.starts_with('-') => bail!("unknown option: {}", arg),
+            _ if port.is_none() => {
+                arg.parse::<u16>()
+                    .map_err(|_| anyhow!("invalid port: {}", arg))?;
+                port = Some(arg);
+            }
+            _ => bail!("unexpected argument: {}", arg),
+        }
+    }
+
+    Ok(Args {
+        port: port.ok_or_else(|| anyhow!("expected a port"))?,
+        hex,
+        check_cksum,
+    })
+}
+
+// TCP-over-UDP segments are checksummed without a pseudo-header
+fn parse_segment(payload: Vec<u8>, check_cksum: bool) -> Result<TCPSegment, ParseError> {
+    let mut seg = TCPSegment::default();
+    if check_cksum {
+        seg.parse(payload.into(), 0)?;
+        return Ok(seg);
+    }
+
+    let mut p = NetParser::new(payload.into());
+    seg.header_mut().parse(&mut p)?;
+    *seg.payload_mut() = p.buffer_mut().take();
+    p.get_result()?;
+    Ok(seg)
+}
+
+fn dump(args: &Args) -> Result<()> {
+    let mut socket = UDPSocket::<NoneAdapter>::try_default()?;
+    socket.bind(&Address::try_from_string("0.0.0.0", args.port.as_str())?)?;
+    eprintln!("listening on UDP port {}", args.port);
+
+    loop {
+        let datagram = socket.recv(None)?;
+        let src = datagram.src_addr().to_string();
+        match parse_segment(datagram.payload, args.check_cksum) {
+            Ok(seg) => {
+                println!(
+                    "{} > {} payload_len={}",
+                    src,
+                    seg.header().summary(),
+                    seg.payload().len()
+                );
+                if args.hex && !seg.payload().is_empty() {
+                    print!("{}", hexdump(seg.payload().as_ref(), 4));
+                }
+            }
+            Err(err) => println!("{} > (failed to parse TCP segment: {})", src, err),
+        }
+    }
+}
+
+fn main() -> ExitCode {
+    let args = match parse_args() {
+        Ok(args) => args,
+        Err(err) => {
+            eprintln!("Error: {}\n{}", err, USAGE);
+            return ExitCode::FAILURE;
+        }
+    };
+    if let Err(err) = dump(&args) {
+        eprintln!("Error: {}", err);
+        return ExitCode::FAILURE;
+    }
+    ExitCode::SUCCESS
+}
@@ -1,29 +1,104 @@
-use libsponge::tcp_helpers::RS144TCPSocket;
+use libsponge::{Address, NoneAdapter, RS144TCPSocket, Socket, TCPSocket};
 
-use std::io::{Read, Write};
-use std::net::TcpStream;
+use anyhow::{Result, anyhow, bail};
+use libc::SHUT_WR;
 
-fn get_url(host: &str, path: &str) -> std::io::Result<()> {
-    println!("Connecting to {}", host);
-    let mut stream = RS144TCPSocket::connect(format!("{}:8080", host))?;
+use std::{
+    env,
+    io::{self, Write},
+    process::ExitCode,
+};
+
+const USAGE: &str = "Usage: webget <host> <path> [--port N] [--kernel|--sponge]";
+
+enum Stack {
+    Kernel,
+    Sponge,
+}
+
+struct Args {
+    host: String,
+    path: String,
+    service: String,
+    stack: Stack,
+}
+
+fn parse_args() -> Result<Args> {
+    let mut positional = Vec::new();
+    let mut service = String::from("http");
+    let mut stack = Stack::Sponge;
+
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--port" => {
+                let port = args.next().ok_or_else(|| anyhow!("--port needs a value"))?;
+                port.parse::<u16>()
+                    .map_err(|_| anyhow!("invalid port: {}", port))?;
+                service = port;
+            }
+            "--kernel" => stack = Stack::Kernel,
+            "--sponge" => stack = Stack::Sponge,
+            _ if arg.starts_with("--") => bail!("unknown option: {}", arg),
+            _ => positional.push(arg),
+        }
+    }
+
+    let [host, path]: [String; 2] = positional
+        .try_into()
+        .map_err(|_| anyhow!("expected a host and a path"))?;
+    Ok(Args {
+        host,
+        path,
+        service,
+        stack,
+    })
+}
+
+fn fetch<P>(socket: &mut Socket<NoneAdapter, P>, host: &str, path: &str) -> Result<()> {
     let request = format!(
         "GET {} HTTP/1.1\r\nHost: {}\r\nConnection: close\r\n\r\n",
         path, host
     );
-    println!("Sending request: {}", request);
-
-    stream.write_all(request.as_bytes())?;
-
-    let mut response = String::new();
-    stream.read_to_string(&mut response)?;
+    socket.write(request.as_str(), true)?;
+    socket.shutdown(SHUT_WR)?;
 
-    println!("{}", response);
+    let mut stdout = io::stdout().lock();
+    while !socket.eof() {
+        stdout.write_all(&socket.read(None)?)?;
+    }
+    stdout.flush()?;
     Ok(())
 }
 
-pub fn main() -> std::io::Result<()> {
-    let host = String::from("localhost");
-    let path = String::from("/hello");
-    get_url(&host, &path)?;
-    Ok(())
+fn get_url(args: &Args) -> Result<()> {
+    let address = Address::try_from_hostname(&args.host, &args.service)?;
+    match args.stack {
+        Stack::Kernel => {
+            let mut socket = TCPSocket::<NoneAdapter>::try_default()?;
+            socket.connect(&address)?;
+            fetch(&mut socket, &args.host, &args.path)
+        }
+        Stack::Sponge => {
+            let mut socket = RS144TCPSocket::try_default()?;
+            socket.connect_to(&address)?;
+            fetch(&mut socket, &args.host, &args.path)?;
+            socket.wait_until_closed()
+        }
+    }
+}
+
+fn main() -> ExitCode {
+    let args = match parse_args() {
+        Ok(args) => args,
+        Err(err) => {
+            eprintln!("Error: {}\n{}", err, USAGE);
+            return ExitCode::FAILURE;
+        }
+    };
+    if let Err(err) = get_url(&args) {
+        eprintln!("Error: {}", err);
+        return ExitCode::FAILURE;
+    }
+    ExitCode::SUCCESS
 }
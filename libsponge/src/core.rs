@@ -10,6 +10,9 @@ pub use tcp_receiver::*;
 pub mod tcp_sender;
 pub use tcp_sender::*;
 
+pub mod congestion_control;
+pub use congestion_control::*;
+
 pub mod wrapping_integers;
 pub use wrapping_integers::*;
 
@@ -18,3 +21,6 @@ pub use tcp_connection::*;
 
 pub mod network_interface;
 pub use network_interface::*;
+
+pub mod router;
+pub use router::*;
@@ -59,7 +59,7 @@ impl ByteStream {
 
     #[inline(always)]
     pub fn buffer_empty(&self) -> bool {
-        self.buffer.len() == 0
+        self.buffer.is_empty()
     }
 
     #[inline(always)]
@@ -0,0 +1,104 @@
+pub mod bbr;
+pub use bbr::*;
+
+pub mod cubic;
+pub use cubic::*;
+
+pub mod new_reno;
+pub use new_reno::*;
+
+#[cfg(test)]
+mod bottleneck;
+
+use crate::Milliseconds;
+
+// what the sender knows when a valid ACK arrives; duplicates have acked_bytes == 0
+#[derive(Debug, Clone, Copy, Default)]
+pub struct AckEvent {
+    pub ackno: u64,
+    pub next_seqno: u64,
+    pub acked_bytes: usize,
+    pub bytes_in_flight: usize,
+    // from the newest segment this ACK covers, unless it was retransmitted
+    pub rtt: Option<Milliseconds>,
+    pub rate: Option<RateSample>,
+    pub now: Milliseconds,
+}
+
+// bytes delivered between sending a segment and receiving its ACK
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RateSample {
+    pub delivered: usize,
+    pub interval: Milliseconds,
+}
+
+impl RateSample {
+    // bytes per ms
+    pub fn bandwidth(&self) -> Option<f64> {
+        let interval = u64::from(self.interval);
+        (interval > 0).then(|| self.delivered as f64 / interval as f64)
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct LossEvent {
+    pub next_seqno: u64,
+    pub bytes_in_flight: usize,
+    pub now: Milliseconds,
+}
+
+pub trait CongestionControl: Send {
+    fn on_ack(&mut self, ack: &AckEvent);
+
+    // a loss inferred from duplicate ACKs
+    fn on_loss(&mut self, loss: &LossEvent);
+
+    fn on_timeout(&mut self, loss: &LossEvent);
+
+    // bytes the sender may have in flight
+    fn cwnd(&self) -> usize;
+
+    // bytes per ms to spread segments out at, if the controller paces at all
+    fn pacing_rate(&self) -> Option<f64> {
+        None
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum CongestionControlKind {
+    None,
+    #[default]
+    NewReno,
+    Cubic,
+    BBR,
+}
+
+impl CongestionControlKind {
+    pub fn build(&self, mss: usize, seed: Option<u64>) -> Box<dyn CongestionControl> {
+        match self {
+            Self::None => Box::new(NoneCongestionControl),
+            Self::NewReno => Box::new(NewReno::new(mss)),
+            Self::Cubic => Box::new(Cubic::new(mss)),
+            Self::BBR => Box::new(match seed {
+                Some(seed) => BBR::with_seed(mss, seed),
+                None => BBR::new(mss),
+            }),
+        }
+    }
+}
+
+// only the receiver's window limits the sender
+#[derive(Debug, Default)]
+pub struct NoneCongestionControl;
+
+impl CongestionControl for NoneCongestionControl {
+    fn on_ack(&mut self, _: &AckEvent) {}
+
+    fn on_loss(&mut self, _: &LossEvent) {}
+
+    fn on_timeout(&mut self, _: &LossEvent) {}
+
+    fn cwnd(&self) -> usize {
+        usize::MAX
+    }
+}
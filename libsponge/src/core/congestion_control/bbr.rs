@@ -0,0 +1,491 @@
+use crate::{AckEvent, CongestionControl, LossEvent, Milliseconds, NewReno};
+
+use rand::{Rng, SeedableRng, rngs::StdRng};
+
+use std::collections::VecDeque;
+
+// 2/ln(2), the smallest gain that still doubles the delivery rate every round
+const HIGH_GAIN: f64 = 2.885;
+const PROBE_BW_CWND_GAIN: f64 = 2.0;
+const PACING_GAIN_CYCLE: [f64; 8] = [1.25, 0.75, 1.0, 1.0, 1.0, 1.0, 1.0, 1.0];
+
+const BW_FILTER_ROUNDS: u64 = 10;
+const MIN_RTT_FILTER_MS: u64 = 10_000;
+const PROBE_RTT_MS: u64 = 200;
+
+// the pipe is full once three rounds in a row fail to grow the bandwidth by a quarter
+const FULL_BW_GROWTH: f64 = 1.25;
+const FULL_BW_ROUNDS: usize = 3;
+
+const MIN_CWND_SEGMENTS: usize = 4;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BBRState {
+    Startup,
+    Drain,
+    ProbeBW,
+    ProbeRTT,
+}
+
+// the largest sample seen over the last `window` rounds
+#[derive(Debug)]
+struct MaxFilter {
+    window: u64,
+    samples: VecDeque<(u64, f64)>,
+}
+
+impl MaxFilter {
+    fn new(window: u64) -> Self {
+        Self {
+            window,
+            samples: VecDeque::new(),
+        }
+    }
+
+    fn update(&mut self, round: u64, value: f64) {
+        while self.samples.back().is_some_and(|&(_, v)| v <= value) {
+            self.samples.pop_back();
+        }
+        self.samples.push_back((round, value));
+        while self
+            .samples
+            .front()
+            .is_some_and(|&(r, _)| r + self.window <= round)
+        {
+            self.samples.pop_front();
+        }
+    }
+
+    fn get(&self) -> f64 {
+        self.samples.front().map_or(0.0, |&(_, v)| v)
+    }
+}
+
+// the model: bottleneck bandwidth in bytes per ms and the round-trip propagation delay
+#[derive(Debug)]
+pub struct BBR {
+    mss: usize,
+    state: BBRState,
+    cwnd: usize,
+    // the window to go back to after a timeout or ProbeRTT
+    prior_cwnd: Option<usize>,
+    pacing_gain: f64,
+    cwnd_gain: f64,
+    max_bw: MaxFilter,
+    min_rtt: Option<Milliseconds>,
+    min_rtt_at: Milliseconds,
+    round: u64,
+    round_end: u64,
+    round_start: bool,
+    full_bw: f64,
+    full_bw_rounds: usize,
+    filled_pipe: bool,
+    cycle_index: usize,
+    cycle_start: Milliseconds,
+    probe_rtt_done_at: Option<Milliseconds>,
+    probe_rtt_round_done: bool,
+    rng: StdRng,
+}
+
+impl BBR {
+    pub fn new(mss: usize) -> Self {
+        Self::with_rng(mss, StdRng::from_os_rng())
+    }
+
+    pub fn with_seed(mss: usize, seed: u64) -> Self {
+        Self::with_rng(mss, StdRng::seed_from_u64(seed))
+    }
+
+    fn with_rng(mss: usize, rng: StdRng) -> Self {
+        Self {
+            mss,
+            state: BBRState::Startup,
+            cwnd: NewReno::initial_window(mss),
+            prior_cwnd: None,
+            pacing_gain: HIGH_GAIN,
+            cwnd_gain: HIGH_GAIN,
+            max_bw: MaxFilter::new(BW_FILTER_ROUNDS),
+            min_rtt: None,
+            min_rtt_at: Milliseconds::default(),
+            round: 0,
+            round_end: 0,
+            round_start: false,
+            full_bw: 0.0,
+            full_bw_rounds: 0,
+            filled_pipe: false,
+            cycle_index: 0,
+            cycle_start: Milliseconds::default(),
+            probe_rtt_done_at: None,
+            probe_rtt_round_done: false,
+            rng,
+        }
+    }
+
+    #[inline(always)]
+    pub fn state(&self) -> BBRState {
+        self.state
+    }
+
+    #[inline(always)]
+    pub fn bandwidth(&self) -> f64 {
+        self.max_bw.get()
+    }
+
+    #[inline(always)]
+    pub fn min_rtt(&self) -> Option<Milliseconds> {
+        self.min_rtt
+    }
+
+    #[inline(always)]
+    pub fn pacing_gain(&self) -> f64 {
+        self.pacing_gain
+    }
+
+    #[inline(always)]
+    pub fn filled_pipe(&self) -> bool {
+        self.filled_pipe
+    }
+
+    fn min_cwnd(&self) -> usize {
+        MIN_CWND_SEGMENTS * self.mss
+    }
+
+    // the bandwidth-delay product, scaled
+    fn bdp(&self, gain: f64) -> Option<usize> {
+        let min_rtt = u64::from(self.min_rtt?) as f64;
+        let bw = self.bandwidth();
+        (bw > 0.0).then_some((gain * bw * min_rtt) as usize)
+    }
+
+    fn update_round(&mut self, ack: &AckEvent) {
+        self.round_start = ack.ackno > self.round_end;
+        if self.round_start {
+            self.round += 1;
+            self.round_end = ack.next_seqno;
+        }
+    }
+
+    fn check_full_pipe(&mut self) {
+        if self.filled_pipe || !self.round_start {
+            return;
+        }
+        let bw = self.bandwidth();
+        if bw >= self.full_bw * FULL_BW_GROWTH {
+            self.full_bw = bw;
+            self.full_bw_rounds = 0;
+            return;
+        }
+        self.full_bw_rounds += 1;
+        self.filled_pipe = self.full_bw_rounds >= FULL_BW_ROUNDS;
+    }
+
+    fn enter_probe_bw(&mut self, now: Milliseconds) {
+        self.state = BBRState::ProbeBW;
+        self.cwnd_gain = PROBE_BW_CWND_GAIN;
+        // start anywhere but the draining phase
+        self.cycle_index = match self.rng.random_range(0..PACING_GAIN_CYCLE.len() - 1) {
+            0 => 0,
+            i => i + 1,
+        };
+        self.pacing_gain = PACING_GAIN_CYCLE[self.cycle_index];
+        self.cycle_start = now;
+    }
+
+    fn advance_cycle(&mut self, ack: &AckEvent) {
+        let Some(min_rtt) = self.min_rtt else {
+            return;
+        };
+        let elapsed = ack.now - self.cycle_start >= min_rtt;
+        let advance = match self.pacing_gain {
+            // keep probing until the extra data actually sits in the network
+            gain if gain > 1.0 => {
+                elapsed && self.bdp(gain).is_some_and(|bdp| ack.bytes_in_flight >= bdp)
+            }
+            // stop draining as soon as the queue is gone
+            gain if gain < 1.0 => {
+                elapsed || self.bdp(1.0).is_some_and(|bdp| ack.bytes_in_flight <= bdp)
+            }
+            _ => elapsed,
+        };
+        if advance {
+            self.cycle_index = (self.cycle_index + 1) % PACING_GAIN_CYCLE.len();
+            self.pacing_gain = PACING_GAIN_CYCLE[self.cycle_index];
+            self.cycle_start = ack.now;
+        }
+    }
+
+    fn update_min_rtt(&mut self, ack: &AckEvent) -> bool {
+        let expired = ack.now - self.min_rtt_at > MIN_RTT_FILTER_MS.into();
+        if let Some(rtt) = ack.rtt
+            && (expired || self.min_rtt.is_none_or(|min| rtt <= min))
+        {
+            self.min_rtt = Some(rtt);
+            self.min_rtt_at = ack.now;
+        }
+        expired
+    }
+
+    fn handle_probe_rtt(&mut self, ack: &AckEvent) {
+        match self.probe_rtt_done_at {
+            None if ack.bytes_in_flight <= self.min_cwnd() => {
+                self.probe_rtt_done_at = Some((u64::from(ack.now) + PROBE_RTT_MS).into());
+                self.probe_rtt_round_done = false;
+                self.round_end = ack.next_seqno;
+            }
+            Some(done_at) => {
+                self.probe_rtt_round_done |= self.round_start;
+                if self.probe_rtt_round_done && ack.now >= done_at {
+                    self.min_rtt_at = ack.now;
+                    self.probe_rtt_done_at = None;
+                    if let Some(prior) = self.prior_cwnd.take() {
+                        self.cwnd = self.cwnd.max(prior);
+                    }
+                    if self.filled_pipe {
+                        self.enter_probe_bw(ack.now);
+                    } else {
+                        self.state = BBRState::Startup;
+                        self.pacing_gain = HIGH_GAIN;
+                        self.cwnd_gain = HIGH_GAIN;
+                    }
+                }
+            }
+            None => {}
+        }
+    }
+
+    fn update_state(&mut self, ack: &AckEvent, min_rtt_expired: bool) {
+        if self.state == BBRState::Startup && self.filled_pipe {
+            self.state = BBRState::Drain;
+            self.pacing_gain = 1.0 / HIGH_GAIN;
+            self.cwnd_gain = HIGH_GAIN;
+        }
+        if self.state == BBRState::Drain
+            && self.bdp(1.0).is_some_and(|bdp| ack.bytes_in_flight <= bdp)
+        {
+            self.enter_probe_bw(ack.now);
+        }
+        if self.state == BBRState::ProbeBW {
+            self.advance_cycle(ack);
+        }
+
+        if self.state != BBRState::ProbeRTT && min_rtt_expired {
+            self.state = BBRState::ProbeRTT;
+            self.pacing_gain = 1.0;
+            self.cwnd_gain = 1.0;
+            self.prior_cwnd = Some(self.prior_cwnd.unwrap_or(self.cwnd).max(self.cwnd));
+            self.probe_rtt_done_at = None;
+        }
+        if self.state == BBRState::ProbeRTT {
+            self.handle_probe_rtt(ack);
+        }
+    }
+
+    fn update_cwnd(&mut self, ack: &AckEvent) {
+        if self.state == BBRState::ProbeRTT {
+            self.cwnd = self.cwnd.min(self.min_cwnd());
+            return;
+        }
+        // a timeout's collapse lasts only until something is acked again
+        if let Some(prior) = self.prior_cwnd.take() {
+            self.cwnd = self.cwnd.max(prior);
+        }
+        // room for a couple of segments in flight beyond the model
+        let target = self
+            .bdp(self.cwnd_gain)
+            .map(|bdp| bdp + 3 * self.mss)
+            .unwrap_or(self.cwnd + ack.acked_bytes);
+        self.cwnd = if self.filled_pipe {
+            (self.cwnd + ack.acked_bytes).min(target)
+        } else {
+            self.cwnd + ack.acked_bytes
+        }
+        .max(self.min_cwnd());
+    }
+}
+
+impl CongestionControl for BBR {
+    fn on_ack(&mut self, ack: &AckEvent) {
+        if ack.acked_bytes == 0 {
+            return;
+        }
+        self.update_round(ack);
+        if let Some(bw) = ack.rate.and_then(|rate| rate.bandwidth()) {
+            self.max_bw.update(self.round, bw);
+        }
+        self.check_full_pipe();
+        let min_rtt_expired = self.update_min_rtt(ack);
+        self.update_state(ack, min_rtt_expired);
+        self.update_cwnd(ack);
+    }
+
+    // the model, not loss, says how fast to go
+    fn on_loss(&mut self, _: &LossEvent) {}
+
+    fn on_timeout(&mut self, _: &LossEvent) {
+        self.prior_cwnd = Some(self.prior_cwnd.unwrap_or(self.cwnd).max(self.cwnd));
+        self.cwnd = self.mss;
+    }
+
+    #[inline(always)]
+    fn cwnd(&self) -> usize {
+        self.cwnd
+    }
+
+    fn pacing_rate(&self) -> Option<f64> {
+        let bw = self.bandwidth();
+        (bw > 0.0).then_some(self.pacing_gain * bw)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        CongestionControlKind, RateSample, TCPConfig, congestion_control::bottleneck::Bottleneck,
+    };
+
+    const MSS: usize = 1000;
+
+    // one ACK for each round's worth of data on a path delivering 1000 bytes/ms with a 20ms RTT
+    fn round(cc: &mut BBR, round: u64, bytes_in_flight: usize, now: u64) {
+        let ackno = round * 100_000;
+        cc.on_ack(&AckEvent {
+            ackno,
+            next_seqno: ackno + 50_000,
+            acked_bytes: 20 * MSS,
+            bytes_in_flight,
+            rtt: Some(20.into()),
+            rate: Some(RateSample {
+                delivered: 20 * MSS,
+                interval: 20.into(),
+            }),
+            now: now.into(),
+        });
+    }
+
+    fn probe_bw(cc: &mut BBR) {
+        for i in 1..=4 {
+            round(cc, i, 100_000, i * 20);
+        }
+        assert_eq!(cc.state(), BBRState::Drain);
+        round(cc, 5, 10_000, 100);
+        assert_eq!(cc.state(), BBRState::ProbeBW);
+    }
+
+    #[test]
+    fn test_max_filter() {
+        let mut filter = MaxFilter::new(3);
+        filter.update(1, 5.0);
+        filter.update(2, 3.0);
+        assert_eq!(filter.get(), 5.0);
+        filter.update(3, 4.0);
+        filter.update(4, 1.0);
+        assert_eq!(filter.get(), 4.0);
+        filter.update(6, 2.0);
+        assert_eq!(filter.get(), 2.0);
+    }
+
+    #[test]
+    fn test_startup_drain_probe_bw() {
+        let mut cc = BBR::with_seed(MSS, 144);
+        assert_eq!(cc.pacing_rate(), None);
+
+        round(&mut cc, 1, 100_000, 20);
+        assert_eq!(cc.state(), BBRState::Startup);
+        assert_eq!(cc.bandwidth(), 1000.0);
+        assert_eq!(cc.min_rtt(), Some(20.into()));
+        assert_eq!(cc.pacing_rate(), Some(HIGH_GAIN * 1000.0));
+
+        // three rounds without more bandwidth: the pipe is full, drain the queue startup built
+        for i in 2..=4 {
+            assert!(!cc.filled_pipe());
+            round(&mut cc, i, 100_000, i * 20);
+        }
+        assert_eq!(cc.state(), BBRState::Drain);
+        assert_eq!(cc.pacing_rate(), Some(1000.0 / HIGH_GAIN));
+
+        round(&mut cc, 5, 10_000, 100);
+        assert_eq!(cc.state(), BBRState::ProbeBW);
+        assert_ne!(cc.pacing_gain(), 0.75);
+
+        // inflight is capped at twice the BDP, plus a few segments
+        for i in 6..20 {
+            round(&mut cc, i, 10_000, i * 20);
+        }
+        assert_eq!(cc.cwnd(), 2 * 20 * MSS + 3 * MSS);
+    }
+
+    #[test]
+    fn test_seeded_cycle_start() {
+        let start = |seed| {
+            let mut cc = BBR::with_seed(MSS, seed);
+            probe_bw(&mut cc);
+            cc.cycle_index
+        };
+        let starts: Vec<usize> = (0..32).map(start).collect();
+        assert_eq!(starts, (0..32).map(start).collect::<Vec<_>>());
+        assert!(!starts.contains(&1));
+        assert!(starts.iter().any(|&i| i != starts[0]));
+    }
+
+    #[test]
+    fn test_probe_rtt() {
+        let mut cc = BBR::with_seed(MSS, 144);
+        probe_bw(&mut cc);
+        let cwnd = cc.cwnd();
+
+        // ten seconds without a lower RTT
+        round(&mut cc, 6, 10_000, 10_200);
+        assert_eq!(cc.state(), BBRState::ProbeRTT);
+        assert_eq!(cc.cwnd(), 4 * MSS);
+
+        // at least 200ms and a round at the minimal window
+        round(&mut cc, 7, 4 * MSS, 10_220);
+        round(&mut cc, 8, 4 * MSS, 10_300);
+        assert_eq!(cc.state(), BBRState::ProbeRTT);
+        round(&mut cc, 9, 4 * MSS, 10_420);
+        assert_eq!(cc.state(), BBRState::ProbeBW);
+        assert_eq!(cc.cwnd(), cwnd);
+    }
+
+    #[test]
+    fn test_loss_keeps_the_model() {
+        let mut cc = BBR::with_seed(MSS, 144);
+        probe_bw(&mut cc);
+        let cwnd = cc.cwnd();
+
+        cc.on_loss(&LossEvent::default());
+        assert_eq!(cc.cwnd(), cwnd);
+
+        cc.on_timeout(&LossEvent::default());
+        cc.on_timeout(&LossEvent::default());
+        assert_eq!(cc.cwnd(), MSS);
+        assert_eq!(cc.bandwidth(), 1000.0);
+        round(&mut cc, 6, 10_000, 120);
+        assert_eq!(cc.cwnd(), cwnd);
+    }
+
+    #[test]
+    fn test_lossy_link() {
+        // ~1 segment per ms, 20ms RTT, a buffer deep enough that only the one data segment
+        // in a hundred lost at random is ever dropped
+        const RATE: usize = 1500;
+        const TOTAL: usize = 2_000_000;
+        let transfer = |kind| {
+            let cfg = TCPConfig {
+                congestion_control: kind,
+                timeout_default: 100,
+                rng_seed: Some(144),
+                ..Default::default()
+            };
+            let mut link = Bottleneck::new(&cfg, RATE, 10, 100).with_loss(u16::MAX / 100, 0, 144);
+            link.transfer(TOTAL, 60_000).expect("transfer stalled")
+        };
+
+        let bbr = transfer(CongestionControlKind::BBR);
+        for kind in [CongestionControlKind::NewReno, CongestionControlKind::Cubic] {
+            let elapsed = transfer(kind);
+            assert!(bbr < elapsed, "BBR {} ms, {:?} {} ms", bbr, kind, elapsed);
+        }
+    }
+}
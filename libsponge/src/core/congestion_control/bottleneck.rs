@@ -0,0 +1,137 @@
+use crate::{TCPConfig, TCPConnection, TCPSegment};
+
+use rand::{Rng, SeedableRng, rngs::StdRng};
+
+use std::collections::VecDeque;
+
+// a one-way bottleneck link between two in-memory connections, simulated in 1ms steps:
+// data segments queue up behind a fixed-rate link and are dropped once the queue is full,
+// ACKs only see the propagation delay
+pub struct Bottleneck {
+    pub sender: TCPConnection,
+    pub receiver: TCPConnection,
+    // bytes the link can carry per ms
+    rate: usize,
+    budget: usize,
+    // one-way propagation delay in ms
+    delay: u64,
+    queue_limit: usize,
+    queue: VecDeque<TCPSegment>,
+    forward: VecDeque<(u64, TCPSegment)>,
+    backward: VecDeque<(u64, TCPSegment)>,
+    now: u64,
+    dropped: usize,
+    received: usize,
+    // random loss on top of the queue's, as in FDAdapterConfig
+    loss_rate_up: u16,
+    loss_rate_dn: u16,
+    rng: StdRng,
+}
+
+impl Bottleneck {
+    pub fn new(cfg: &TCPConfig, rate: usize, delay: u64, queue_limit: usize) -> Self {
+        let mut sender = TCPConnection::with_config(cfg);
+        let receiver = TCPConnection::with_config(cfg);
+        sender.connect();
+        Self {
+            sender,
+            receiver,
+            rate,
+            budget: 0,
+            delay,
+            queue_limit,
+            queue: VecDeque::new(),
+            forward: VecDeque::new(),
+            backward: VecDeque::new(),
+            now: 0,
+            dropped: 0,
+            received: 0,
+            loss_rate_up: 0,
+            loss_rate_dn: 0,
+            rng: StdRng::seed_from_u64(0),
+        }
+    }
+
+    pub fn with_loss(mut self, loss_rate_up: u16, loss_rate_dn: u16, seed: u64) -> Self {
+        self.loss_rate_up = loss_rate_up;
+        self.loss_rate_dn = loss_rate_dn;
+        self.rng = StdRng::seed_from_u64(seed);
+        self
+    }
+
+    fn lost(&mut self, loss: u16) -> bool {
+        loss != 0 && self.rng.random::<u16>() < loss
+    }
+
+    #[inline(always)]
+    pub fn dropped(&self) -> usize {
+        self.dropped
+    }
+
+    fn wire_size(seg: &TCPSegment) -> usize {
+        seg.header().length() + seg.payload().len()
+    }
+
+    pub fn step(&mut self) {
+        while let Some(seg) = self.sender.segments_out_mut().pop_front() {
+            if self.lost(self.loss_rate_up) {
+                self.dropped += 1;
+            } else if self.queue.len() < self.queue_limit {
+                self.queue.push_back(seg);
+            } else {
+                self.dropped += 1;
+            }
+        }
+
+        self.budget += self.rate;
+        while let Some(seg) = self.queue.front() {
+            let size = Self::wire_size(seg);
+            if size > self.budget {
+                break;
+            }
+            self.budget -= size;
+            let seg = self.queue.pop_front().unwrap();
+            self.forward.push_back((self.now + self.delay, seg));
+        }
+        // an idle link doesn't save up capacity
+        if self.queue.is_empty() {
+            self.budget = self.budget.min(self.rate);
+        }
+
+        while self.forward.front().is_some_and(|(at, _)| *at <= self.now) {
+            let (_, seg) = self.forward.pop_front().unwrap();
+            self.receiver.segment_received(&seg);
+        }
+        while let Some(seg) = self.receiver.segments_out_mut().pop_front() {
+            if !self.lost(self.loss_rate_dn) {
+                self.backward.push_back((self.now + self.delay, seg));
+            }
+        }
+        while self.backward.front().is_some_and(|(at, _)| *at <= self.now) {
+            let (_, seg) = self.backward.pop_front().unwrap();
+            self.sender.segment_received(&seg);
+        }
+
+        let inbound = self.receiver.inbound_stream_mut();
+        self.received += inbound.buffer_size();
+        inbound.pop_output(inbound.buffer_size());
+
+        self.sender.tick(1.into());
+        self.receiver.tick(1.into());
+        self.now += 1;
+    }
+
+    // pushes `total` bytes through the link, returning the ms it took
+    pub fn transfer(&mut self, total: usize, limit_ms: u64) -> Option<u64> {
+        let data = vec![0; total];
+        let mut written = 0;
+        while self.received < total {
+            if self.now >= limit_ms || !self.sender.active() {
+                return None;
+            }
+            written += self.sender.write(&data[written..]);
+            self.step();
+        }
+        Some(self.now)
+    }
+}
@@ -0,0 +1,455 @@
+use crate::{AckEvent, CongestionControl, LossEvent, Milliseconds, NewReno};
+
+// RFC 9438 constants
+const C: f64 = 0.4;
+const BETA: f64 = 0.7;
+const ALPHA: f64 = 3.0 * (1.0 - BETA) / (1.0 + BETA);
+
+// HyStart++ (RFC 9406) delay-increase detection
+const HYSTART_MIN_SAMPLES: usize = 8;
+const HYSTART_LOW_WINDOW: usize = 16;
+const HYSTART_MIN_RTT_THRESH: u64 = 4;
+const HYSTART_MAX_RTT_THRESH: u64 = 16;
+
+#[derive(Debug, Default)]
+struct HyStart {
+    round_end: u64,
+    last_round_min_rtt: Option<Milliseconds>,
+    current_round_min_rtt: Option<Milliseconds>,
+    samples: usize,
+}
+
+impl HyStart {
+    // whether the RTT has grown enough in this round to leave slow start
+    fn on_ack(&mut self, ack: &AckEvent) -> bool {
+        if ack.ackno > self.round_end {
+            self.round_end = ack.next_seqno;
+            self.last_round_min_rtt = self.current_round_min_rtt.take();
+            self.samples = 0;
+        }
+        let Some(rtt) = ack.rtt else {
+            return false;
+        };
+        self.current_round_min_rtt =
+            Some(self.current_round_min_rtt.map_or(rtt, |min| min.min(rtt)));
+        self.samples += 1;
+
+        match (self.last_round_min_rtt, self.current_round_min_rtt) {
+            (Some(last), Some(current)) if self.samples >= HYSTART_MIN_SAMPLES => {
+                let thresh =
+                    (u64::from(last) / 8).clamp(HYSTART_MIN_RTT_THRESH, HYSTART_MAX_RTT_THRESH);
+                u64::from(current) >= u64::from(last) + thresh
+            }
+            _ => false,
+        }
+    }
+}
+
+// windows are kept in bytes, the cubic function works in segments and seconds
+#[derive(Debug)]
+pub struct Cubic {
+    mss: f64,
+    cwnd: f64,
+    ssthresh: f64,
+    w_max: f64,
+    cwnd_prior: f64,
+    k: f64,
+    epoch_start: Option<Milliseconds>,
+    // Reno-friendly window estimate
+    w_est: f64,
+    srtt: Option<f64>,
+    recover: Option<u64>,
+    // the fast recovery window inflation for segments that have left the network
+    inflation: f64,
+    hystart: HyStart,
+    fast_convergence: bool,
+}
+
+impl Cubic {
+    pub fn new(mss: usize) -> Self {
+        let mss = mss as f64;
+        Self {
+            mss,
+            cwnd: NewReno::initial_window(mss as usize) as f64,
+            ssthresh: f64::INFINITY,
+            w_max: 0.0,
+            cwnd_prior: 0.0,
+            k: 0.0,
+            epoch_start: None,
+            w_est: 0.0,
+            srtt: None,
+            recover: None,
+            inflation: 0.0,
+            hystart: HyStart::default(),
+            fast_convergence: true,
+        }
+    }
+
+    pub fn without_fast_convergence(mut self) -> Self {
+        self.fast_convergence = false;
+        self
+    }
+
+    #[inline(always)]
+    pub fn ssthresh(&self) -> f64 {
+        self.ssthresh
+    }
+
+    #[inline(always)]
+    pub fn w_max(&self) -> f64 {
+        self.w_max
+    }
+
+    #[inline(always)]
+    pub fn k(&self) -> f64 {
+        self.k
+    }
+
+    #[inline(always)]
+    pub fn in_slow_start(&self) -> bool {
+        self.cwnd < self.ssthresh
+    }
+
+    // W_cubic(t) in bytes, t in seconds since the epoch started
+    fn w_cubic(&self, t: f64) -> f64 {
+        (C * (t - self.k).powi(3) + self.w_max / self.mss) * self.mss
+    }
+
+    fn update_srtt(&mut self, rtt: Option<Milliseconds>) {
+        if let Some(rtt) = rtt {
+            let rtt = u64::from(rtt) as f64 / 1000.0;
+            self.srtt = Some(self.srtt.map_or(rtt, |srtt| 0.875 * srtt + 0.125 * rtt));
+        }
+    }
+
+    fn start_epoch(&mut self, now: Milliseconds) {
+        self.epoch_start = Some(now);
+        self.w_est = self.cwnd;
+        self.k = if self.cwnd < self.w_max {
+            ((self.w_max - self.cwnd) / self.mss / C).cbrt()
+        } else {
+            self.w_max = self.cwnd;
+            0.0
+        };
+    }
+
+    fn congestion_avoidance(&mut self, ack: &AckEvent) {
+        if self.epoch_start.is_none() {
+            self.start_epoch(ack.now);
+        }
+        let epoch_start = self.epoch_start.unwrap();
+
+        let acked = ack.acked_bytes as f64;
+        let t = u64::from(ack.now - epoch_start) as f64 / 1000.0;
+        let rtt = self.srtt.unwrap_or(0.0);
+
+        // once the estimate reaches the window before the last reduction, grow like Reno
+        let alpha = if self.w_est >= self.cwnd_prior {
+            1.0
+        } else {
+            ALPHA
+        };
+        self.w_est += alpha * self.mss * acked / self.cwnd;
+
+        if self.w_cubic(t) < self.w_est {
+            self.cwnd = self.w_est;
+        } else {
+            let target = self.w_cubic(t + rtt).clamp(self.cwnd, 1.5 * self.cwnd);
+            self.cwnd += (target - self.cwnd) / self.cwnd * acked;
+        }
+    }
+
+    // shared by both kinds of congestion event
+    fn reduce(&mut self) {
+        self.epoch_start = None;
+        self.w_max = if self.fast_convergence && self.cwnd < self.w_max {
+            // release bandwidth to newer flows
+            self.cwnd * (1.0 + BETA) / 2.0
+        } else {
+            self.cwnd
+        };
+        self.cwnd_prior = self.cwnd;
+        self.ssthresh = (self.cwnd * BETA).max(2.0 * self.mss);
+    }
+}
+
+impl CongestionControl for Cubic {
+    fn on_ack(&mut self, ack: &AckEvent) {
+        self.update_srtt(ack.rtt);
+        if let Some(recover) = self.recover {
+            if ack.acked_bytes == 0 {
+                self.inflation += self.mss;
+                return;
+            }
+            if ack.ackno < recover {
+                // partial ACK: deflate by what was acked, but let one new segment out
+                self.inflation = (self.inflation - ack.acked_bytes as f64).max(0.0);
+                if ack.acked_bytes as f64 >= self.mss {
+                    self.inflation += self.mss;
+                }
+                return;
+            }
+            // full ACK: leave recovery without bursting out what it held back
+            self.recover = None;
+            self.inflation = 0.0;
+            self.cwnd = self
+                .cwnd
+                .min((ack.bytes_in_flight as f64).max(self.mss) + self.mss);
+            return;
+        }
+        if ack.acked_bytes == 0 {
+            return;
+        }
+
+        if self.in_slow_start() {
+            let delay_increased = self.hystart.on_ack(ack);
+            if delay_increased && self.cwnd >= HYSTART_LOW_WINDOW as f64 * self.mss {
+                self.ssthresh = self.cwnd;
+                return;
+            }
+            self.cwnd += (ack.acked_bytes as f64).min(self.mss);
+            return;
+        }
+        self.congestion_avoidance(ack);
+    }
+
+    fn on_loss(&mut self, loss: &LossEvent) {
+        if self.recover.is_some() {
+            return;
+        }
+        self.reduce();
+        self.cwnd = self.ssthresh;
+        self.inflation = 3.0 * self.mss;
+        self.recover = Some(loss.next_seqno);
+    }
+
+    fn on_timeout(&mut self, _: &LossEvent) {
+        // back-to-back timeouts of the same segment only shrink the window once
+        if self.cwnd > self.mss {
+            self.reduce();
+        }
+        self.cwnd = self.mss;
+        self.recover = None;
+        self.inflation = 0.0;
+    }
+
+    #[inline(always)]
+    fn cwnd(&self) -> usize {
+        (self.cwnd + self.inflation) as usize
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        CongestionControlKind, TCPConfig, TCPHeader, TCPSender, WrappingU32,
+        congestion_control::bottleneck::Bottleneck,
+    };
+
+    const MSS: usize = 1000;
+
+    fn ack(cc: &mut Cubic, ackno: u64, rtt: Option<u64>, now: u64) {
+        cc.on_ack(&AckEvent {
+            ackno,
+            next_seqno: ackno + cc.cwnd() as u64,
+            acked_bytes: MSS,
+            bytes_in_flight: cc.cwnd(),
+            rtt: rtt.map(Milliseconds::from),
+            now: now.into(),
+            ..Default::default()
+        });
+    }
+
+    fn loss(cc: &mut Cubic, next_seqno: u64) {
+        cc.on_loss(&LossEvent {
+            next_seqno,
+            ..Default::default()
+        });
+    }
+
+    // slow start without RTT samples up to `segments` MSS
+    fn grow_to(cc: &mut Cubic, segments: usize) {
+        while cc.cwnd() < segments * MSS {
+            ack(cc, 0, None, 0);
+        }
+    }
+
+    // acks a window's worth of data every `rtt` ms until `until`
+    fn run(cc: &mut Cubic, from: u64, until: u64, rtt: u64) {
+        let mut ackno = 1_000_000;
+        for now in (from..until).step_by(rtt as usize) {
+            for _ in 0..cc.cwnd() / MSS {
+                ackno += MSS as u64;
+                ack(cc, ackno, Some(rtt), now);
+            }
+        }
+    }
+
+    #[test]
+    fn test_reduction_and_fast_convergence() {
+        let mut cc = Cubic::new(MSS);
+        grow_to(&mut cc, 100);
+        loss(&mut cc, 0);
+        assert_eq!(cc.cwnd, (70 * MSS) as f64);
+        assert_eq!(cc.w_max(), (100 * MSS) as f64);
+
+        // further losses before the recovery point is acked are the same event
+        loss(&mut cc, 0);
+        assert_eq!(cc.cwnd, (70 * MSS) as f64);
+
+        // losing again below the previous w_max gives up some of it
+        ack(&mut cc, 1, None, 0);
+        let cwnd = cc.cwnd;
+        loss(&mut cc, 1);
+        assert!((cc.cwnd - cwnd * 0.7).abs() < 1e-6);
+        assert!((cc.w_max() - cwnd * 0.85).abs() < 1e-6);
+
+        let mut cc = Cubic::new(MSS).without_fast_convergence();
+        grow_to(&mut cc, 100);
+        loss(&mut cc, 0);
+        ack(&mut cc, 1, None, 0);
+        let cwnd = cc.cwnd;
+        loss(&mut cc, 1);
+        assert_eq!(cc.w_max(), cwnd);
+    }
+
+    #[test]
+    fn test_fast_recovery() {
+        let mut cc = Cubic::new(MSS);
+        grow_to(&mut cc, 100);
+
+        // the three duplicates that signalled the loss, then one more
+        loss(&mut cc, 100_000);
+        assert_eq!(cc.cwnd(), 73 * MSS);
+        cc.on_ack(&AckEvent {
+            ackno: 0,
+            ..Default::default()
+        });
+        assert_eq!(cc.cwnd(), 74 * MSS);
+
+        // a partial ACK takes back what it acked, but lets one new segment out
+        cc.on_ack(&AckEvent {
+            ackno: 3_000,
+            acked_bytes: 3 * MSS,
+            ..Default::default()
+        });
+        assert_eq!(cc.cwnd(), 72 * MSS);
+
+        // the full ACK leaves no more room than the data still in flight, plus one segment
+        cc.on_ack(&AckEvent {
+            ackno: 100_000,
+            acked_bytes: 97 * MSS,
+            bytes_in_flight: 20 * MSS,
+            ..Default::default()
+        });
+        assert_eq!(cc.cwnd(), 21 * MSS);
+        assert_eq!(cc.ssthresh(), (70 * MSS) as f64);
+    }
+
+    #[test]
+    fn test_concave_then_convex_growth() {
+        let mut cc = Cubic::new(MSS);
+        grow_to(&mut cc, 100);
+        loss(&mut cc, 0);
+        let w_max = cc.w_max();
+
+        // K = cbrt(30 / 0.4) seconds to climb back to w_max
+        run(&mut cc, 0, 100, 100);
+        assert!((cc.k() - 75f64.cbrt()).abs() < 1e-9);
+        let k = (cc.k() * 1000.0) as u64;
+
+        // most of the way there after half of K, then a plateau around w_max
+        run(&mut cc, 100, k / 2, 100);
+        let cwnd = cc.cwnd() as f64;
+        assert!(cwnd > 0.93 * w_max && cwnd < w_max, "{}", cwnd);
+
+        run(&mut cc, k / 2, k, 100);
+        let cwnd = cc.cwnd() as f64;
+        assert!((cwnd - w_max).abs() < 0.02 * w_max, "{}", cwnd);
+
+        // and probing beyond it faster and faster
+        run(&mut cc, k, k + 2000, 100);
+        let cwnd = cc.cwnd() as f64;
+        assert!(cwnd > 1.05 * w_max, "{}", cwnd);
+    }
+
+    #[test]
+    fn test_tcp_friendly_region() {
+        // with a short RTT Reno would regain the window long before K
+        let mut cc = Cubic::new(MSS);
+        grow_to(&mut cc, 10);
+        loss(&mut cc, 0);
+        let w_max = cc.w_max();
+
+        run(&mut cc, 0, 1000, 10);
+        assert!(cc.k() > 1.0);
+        assert!(cc.cwnd() as f64 > 2.0 * w_max, "{}", cc.cwnd());
+    }
+
+    #[test]
+    fn test_hystart_exit() {
+        let mut cc = Cubic::new(MSS);
+        grow_to(&mut cc, 16);
+
+        // a round whose RTT barely grew keeps slow start going
+        let mut ackno = 0;
+        for (round, rtt) in [(0, 100), (1, 105)] {
+            for _ in 0..8 {
+                ackno += MSS as u64;
+                ack(&mut cc, ackno, Some(rtt), round * 100);
+            }
+            ackno += 100 * MSS as u64;
+        }
+        assert!(cc.in_slow_start());
+
+        for i in 0..8 {
+            assert!(cc.in_slow_start(), "{}", i);
+            ackno += MSS as u64;
+            ack(&mut cc, ackno, Some(125), 200);
+        }
+        assert!(!cc.in_slow_start());
+        assert_eq!(cc.ssthresh(), cc.cwnd() as f64);
+    }
+
+    #[test]
+    fn test_timeout_through_sender() {
+        let cfg = TCPConfig {
+            congestion_control: CongestionControlKind::Cubic,
+            fixed_isn: Some(WrappingU32::new(0)),
+            ..Default::default()
+        };
+        let mut sender = TCPSender::with_config(&cfg);
+        sender.fill_window();
+        sender.ack_received(&WrappingU32::new(1), 60000, false);
+        sender.stream_in_mut().write(&[0; 20000]);
+        sender.fill_window();
+        assert!(sender.cwnd() > TCPConfig::MAX_PAYLOAD_SIZE);
+
+        sender.tick((cfg.timeout_default as u64).into());
+        assert_eq!(sender.consq_retxs(), 1);
+        assert_eq!(sender.cwnd(), TCPConfig::MAX_PAYLOAD_SIZE);
+    }
+
+    #[test]
+    fn test_bottleneck() {
+        // ~1 segment per ms, 20ms RTT, 10 segments of buffer: the receiver's window
+        // is larger than the path can hold, so the sender has to find the limit
+        const RATE: usize = 1500;
+        const TOTAL: usize = 2_000_000;
+
+        let cfg = TCPConfig {
+            congestion_control: CongestionControlKind::Cubic,
+            timeout_default: 100,
+            ..Default::default()
+        };
+        let mut link = Bottleneck::new(&cfg, RATE, 10, 10);
+        let elapsed = link.transfer(TOTAL, 20_000).expect("transfer stalled");
+        assert!(link.dropped() > 0);
+
+        let segments = TOTAL.div_ceil(TCPConfig::MAX_PAYLOAD_SIZE);
+        let ideal = (segments * (TCPConfig::MAX_PAYLOAD_SIZE + TCPHeader::LENGTH) / RATE) as u64;
+        assert!(elapsed < ideal * 2, "{} ms, ideal {} ms", elapsed, ideal);
+        assert!(link.dropped() < segments / 20, "{} dropped", link.dropped());
+    }
+}
@@ -0,0 +1,186 @@
+use crate::{AckEvent, CongestionControl, LossEvent};
+
+// RFC 5681 slow start and congestion avoidance, RFC 6582 fast recovery
+#[derive(Debug)]
+pub struct NewReno {
+    mss: usize,
+    cwnd: usize,
+    ssthresh: usize,
+    // bytes acked since cwnd last grew in congestion avoidance
+    acked_in_ca: usize,
+    // the sequence number that ends fast recovery once acked
+    recover: Option<u64>,
+}
+
+impl NewReno {
+    pub fn new(mss: usize) -> Self {
+        Self {
+            mss,
+            cwnd: Self::initial_window(mss),
+            ssthresh: usize::MAX,
+            acked_in_ca: 0,
+            recover: None,
+        }
+    }
+
+    // RFC 5681 section 3.1
+    pub fn initial_window(mss: usize) -> usize {
+        match mss {
+            0..=1095 => 4 * mss,
+            1096..=2190 => 3 * mss,
+            _ => 2 * mss,
+        }
+    }
+
+    #[inline(always)]
+    pub fn ssthresh(&self) -> usize {
+        self.ssthresh
+    }
+
+    #[inline(always)]
+    pub fn in_recovery(&self) -> bool {
+        self.recover.is_some()
+    }
+
+    fn reduced_ssthresh(&self, bytes_in_flight: usize) -> usize {
+        (bytes_in_flight / 2).max(2 * self.mss)
+    }
+}
+
+impl CongestionControl for NewReno {
+    fn on_ack(&mut self, ack: &AckEvent) {
+        if let Some(recover) = self.recover {
+            if ack.acked_bytes == 0 {
+                // every further duplicate means another segment left the network
+                self.cwnd += self.mss;
+            } else if ack.ackno >= recover {
+                // full ACK: deflate the window and leave recovery
+                self.cwnd = self
+                    .ssthresh
+                    .min(ack.bytes_in_flight.max(self.mss) + self.mss);
+                self.recover = None;
+            } else {
+                // partial ACK: deflate by what was acked, but let one new segment out
+                self.cwnd = self.cwnd.saturating_sub(ack.acked_bytes).max(self.mss);
+                if ack.acked_bytes >= self.mss {
+                    self.cwnd += self.mss;
+                }
+            }
+            return;
+        }
+
+        if ack.acked_bytes == 0 {
+            return;
+        }
+        if self.cwnd < self.ssthresh {
+            self.cwnd += ack.acked_bytes.min(self.mss);
+            return;
+        }
+        self.acked_in_ca += ack.acked_bytes;
+        if self.acked_in_ca >= self.cwnd {
+            self.acked_in_ca -= self.cwnd;
+            self.cwnd += self.mss;
+        }
+    }
+
+    fn on_loss(&mut self, loss: &LossEvent) {
+        if self.recover.is_some() {
+            return;
+        }
+        self.ssthresh = self.reduced_ssthresh(loss.bytes_in_flight);
+        self.cwnd = self.ssthresh + 3 * self.mss;
+        self.acked_in_ca = 0;
+        self.recover = Some(loss.next_seqno);
+    }
+
+    fn on_timeout(&mut self, loss: &LossEvent) {
+        self.ssthresh = self.reduced_ssthresh(loss.bytes_in_flight);
+        self.cwnd = self.mss;
+        self.acked_in_ca = 0;
+        self.recover = None;
+    }
+
+    #[inline(always)]
+    fn cwnd(&self) -> usize {
+        self.cwnd
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MSS: usize = 1000;
+
+    fn ack(cc: &mut NewReno, ackno: u64, acked_bytes: usize, bytes_in_flight: usize) {
+        cc.on_ack(&AckEvent {
+            ackno,
+            acked_bytes,
+            bytes_in_flight,
+            ..Default::default()
+        });
+    }
+
+    #[test]
+    fn test_slow_start_and_congestion_avoidance() {
+        let mut cc = NewReno::new(MSS);
+        assert_eq!(cc.cwnd(), 4 * MSS);
+
+        // slow start: one MSS per ACK, duplicates don't count
+        ack(&mut cc, 1000, MSS, 3 * MSS);
+        ack(&mut cc, 1000, 0, 3 * MSS);
+        assert_eq!(cc.cwnd(), 5 * MSS);
+
+        cc.on_timeout(&LossEvent {
+            bytes_in_flight: 10 * MSS,
+            ..Default::default()
+        });
+        assert_eq!((cc.cwnd(), cc.ssthresh()), (MSS, 5 * MSS));
+
+        for _ in 0..4 {
+            ack(&mut cc, 0, MSS, 0);
+        }
+        assert_eq!(cc.cwnd(), 5 * MSS);
+
+        // congestion avoidance: one MSS per window's worth of ACKs
+        for _ in 0..4 {
+            ack(&mut cc, 0, MSS, 0);
+        }
+        assert_eq!(cc.cwnd(), 5 * MSS);
+        ack(&mut cc, 0, MSS, 0);
+        assert_eq!(cc.cwnd(), 6 * MSS);
+    }
+
+    #[test]
+    fn test_fast_recovery() {
+        let mut cc = NewReno::new(MSS);
+        cc.on_loss(&LossEvent {
+            next_seqno: 20_000,
+            bytes_in_flight: 10 * MSS,
+            ..Default::default()
+        });
+        assert!(cc.in_recovery());
+        assert_eq!((cc.cwnd(), cc.ssthresh()), (8 * MSS, 5 * MSS));
+
+        // a second loss signal in the same window changes nothing
+        cc.on_loss(&LossEvent {
+            next_seqno: 30_000,
+            bytes_in_flight: 20 * MSS,
+            ..Default::default()
+        });
+        assert_eq!(cc.cwnd(), 8 * MSS);
+
+        ack(&mut cc, 10_000, 0, 10 * MSS);
+        assert_eq!(cc.cwnd(), 9 * MSS);
+
+        // partial ACK stays in recovery
+        ack(&mut cc, 13_000, 3 * MSS, 7 * MSS);
+        assert!(cc.in_recovery());
+        assert_eq!(cc.cwnd(), 7 * MSS);
+
+        // full ACK leaves it with cwnd no larger than ssthresh
+        ack(&mut cc, 20_000, 7 * MSS, 0);
+        assert!(!cc.in_recovery());
+        assert_eq!(cc.cwnd(), 2 * MSS);
+    }
+}
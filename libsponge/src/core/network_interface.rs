@@ -1,4 +1,7 @@
-use crate::{Address, EthernetAddress, EthernetFrame, InternetDatagram, Milliseconds};
+use crate::{
+    ARPMessage, Address, Buffer, BufferList, ETHERNETBROADCAST, EthernetAddress, EthernetFrame,
+    EthernetHeader, IPv4NUM, InternetDatagram, Milliseconds,
+};
 
 use std::collections::{HashMap, VecDeque};
 
@@ -23,4 +26,277 @@ pub struct NetworkInterface {
 impl NetworkInterface {
     const MAX_RETX_WAITING_TIME: usize = 5000;
     const MAX_CACHE_TIME: usize = 30000;
+
+    fn ip_num(&self) -> u32 {
+        TryInto::<IPv4NUM>::try_into(&self.ip_addr)
+            .expect("NetworkInterface requires an IPv4 address")
+            .0
+    }
+
+    fn send_frame(&mut self, dst: EthernetAddress, ty: u16, payload: BufferList) {
+        let mut frame = EthernetFrame::default();
+        *frame.header_mut() = EthernetHeader {
+            dst,
+            src: self.ethernet_addr,
+            ty,
+        };
+        *frame.payload_mut() = payload;
+        self.frames_out.push_back(frame);
+    }
+
+    fn send_arp(&mut self, opcode: u16, target_ethernet_address: EthernetAddress, target_ip: u32) {
+        let msg = ARPMessage {
+            opcode,
+            sender_ethernet_address: self.ethernet_addr,
+            sender_ip_address: self.ip_num(),
+            target_ethernet_address,
+            target_ip_address: target_ip,
+            ..Default::default()
+        };
+        let dst = match opcode {
+            ARPMessage::OPCODE_REQUEST => ETHERNETBROADCAST,
+            _ => target_ethernet_address,
+        };
+        let payload = msg
+            .serialize()
+            .expect("ARP messages built by NetworkInterface are always supported");
+        self.send_frame(dst, EthernetHeader::TYPE_ARP, payload.into());
+    }
+
+    fn send_ipv4(&mut self, dgram: &InternetDatagram, dst: EthernetAddress) {
+        match dgram.try_serialize() {
+            Ok(payload) => self.send_frame(dst, EthernetHeader::TYPE_IPV4, payload),
+            Err(err) => eprintln!(
+                "NetworkInterface: dropping unserializable datagram: {}",
+                err
+            ),
+        }
+    }
+
+    fn learn(&mut self, ip: u32, mac_addr: EthernetAddress) {
+        self.cache.insert(
+            ip,
+            EthernetAddressEntry {
+                caching_time: 0.into(),
+                mac_addr,
+            },
+        );
+        if let Some(waiting) = self.queue_map.remove(&ip) {
+            waiting
+                .waiting_datagram
+                .iter()
+                .for_each(|dgram| self.send_ipv4(dgram, mac_addr));
+        }
+    }
+}
+
+impl NetworkInterface {
+    pub fn new(ethernet_addr: EthernetAddress, ip_addr: Address) -> Self {
+        Self {
+            ethernet_addr,
+            ip_addr,
+            frames_out: VecDeque::new(),
+            cache: HashMap::new(),
+            queue_map: HashMap::new(),
+        }
+    }
+
+    pub fn send_datagram(&mut self, dgram: &InternetDatagram, next_hop: &Address) {
+        let next_hop_ip = match TryInto::<IPv4NUM>::try_into(next_hop) {
+            Ok(ip) => ip.0,
+            Err(err) => {
+                eprintln!("NetworkInterface: invalid next hop: {}", err);
+                return;
+            }
+        };
+
+        if let Some(entry) = self.cache.get(&next_hop_ip) {
+            let mac_addr = entry.mac_addr;
+            return self.send_ipv4(dgram, mac_addr);
+        }
+
+        let retx_waiting_time = Milliseconds::from(Self::MAX_RETX_WAITING_TIME as u64);
+        let waiting = self
+            .queue_map
+            .entry(next_hop_ip)
+            .or_insert_with(|| WaitingList {
+                ms_since_last_arp_sent: retx_waiting_time,
+                waiting_datagram: VecDeque::new(),
+            });
+        waiting.waiting_datagram.push_back(dgram.clone());
+        if waiting.ms_since_last_arp_sent >= retx_waiting_time {
+            waiting.ms_since_last_arp_sent = 0.into();
+            self.send_arp(
+                ARPMessage::OPCODE_REQUEST,
+                EthernetAddress::default(),
+                next_hop_ip,
+            );
+        }
+    }
+
+    pub fn recv_frame(&mut self, frame: &EthernetFrame) -> Option<InternetDatagram> {
+        let header = frame.header();
+        if header.dst != self.ethernet_addr && header.dst != ETHERNETBROADCAST {
+            return None;
+        }
+
+        let payload = Buffer::from(Into::<Vec<u8>>::into(frame.payload()));
+        match header.ty {
+            EthernetHeader::TYPE_IPV4 => {
+                let mut dgram = InternetDatagram::default();
+                dgram.try_parse(payload).ok()?;
+                Some(dgram)
+            }
+            EthernetHeader::TYPE_ARP => {
+                let mut msg = ARPMessage::default();
+                msg.parse(payload).ok()?;
+                self.learn(msg.sender_ip_address, msg.sender_ethernet_address);
+                if msg.opcode == ARPMessage::OPCODE_REQUEST
+                    && msg.target_ip_address == self.ip_num()
+                {
+                    self.send_arp(
+                        ARPMessage::OPCODE_REPLY,
+                        msg.sender_ethernet_address,
+                        msg.sender_ip_address,
+                    );
+                }
+                None
+            }
+            _ => None,
+        }
+    }
+
+    pub fn tick(&mut self, ms_since_last_tick: Milliseconds) {
+        let max_cache_time = Milliseconds::from(Self::MAX_CACHE_TIME as u64);
+        self.cache.retain(|_, entry| {
+            entry.caching_time += ms_since_last_tick;
+            entry.caching_time < max_cache_time
+        });
+        self.queue_map
+            .values_mut()
+            .for_each(|waiting| waiting.ms_since_last_arp_sent += ms_since_last_tick);
+    }
+
+    #[inline(always)]
+    pub fn ethernet_addr(&self) -> &EthernetAddress {
+        &self.ethernet_addr
+    }
+
+    #[inline(always)]
+    pub fn frames_out(&self) -> &VecDeque<EthernetFrame> {
+        &self.frames_out
+    }
+
+    #[inline(always)]
+    pub fn frames_out_mut(&mut self) -> &mut VecDeque<EthernetFrame> {
+        &mut self.frames_out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ip(a: u8, b: u8, c: u8, d: u8) -> u32 {
+        u32::from_be_bytes([a, b, c, d])
+    }
+
+    fn datagram(src: u32, dst: u32) -> InternetDatagram {
+        let mut dgram = InternetDatagram::default();
+        dgram.header_mut().src = src;
+        dgram.header_mut().dst = dst;
+        dgram.header_mut().len = 20 + 5;
+        *dgram.payload_mut() = b"hello".to_vec().into();
+        dgram
+    }
+
+    fn arp_of(frame: &EthernetFrame) -> ARPMessage {
+        let mut msg = ARPMessage::default();
+        msg.parse(Buffer::from(Into::<Vec<u8>>::into(frame.payload())))
+            .unwrap();
+        msg
+    }
+
+    #[test]
+    fn test_arp_resolution() {
+        let local_mac = EthernetAddress([0x02, 0, 0, 0, 0, 0x01]);
+        let remote_mac = EthernetAddress([0x02, 0, 0, 0, 0, 0x02]);
+        let local_ip = ip(10, 0, 0, 1);
+        let remote_ip = ip(10, 0, 0, 2);
+        let mut iface = NetworkInterface::new(local_mac, IPv4NUM(local_ip).into());
+
+        // unknown next hop: queue the datagram and broadcast one ARP request
+        iface.send_datagram(&datagram(local_ip, remote_ip), &IPv4NUM(remote_ip).into());
+        iface.send_datagram(&datagram(local_ip, remote_ip), &IPv4NUM(remote_ip).into());
+        assert_eq!(iface.frames_out().len(), 1);
+        let request = iface.frames_out_mut().pop_front().unwrap();
+        assert_eq!(request.header().dst, ETHERNETBROADCAST);
+        assert_eq!(request.header().ty, EthernetHeader::TYPE_ARP);
+        assert_eq!(arp_of(&request).target_ip_address, remote_ip);
+
+        // the reply releases both pending datagrams
+        let mut reply = EthernetFrame::default();
+        *reply.header_mut() = EthernetHeader {
+            dst: local_mac,
+            src: remote_mac,
+            ty: EthernetHeader::TYPE_ARP,
+        };
+        let msg = ARPMessage {
+            opcode: ARPMessage::OPCODE_REPLY,
+            sender_ethernet_address: remote_mac,
+            sender_ip_address: remote_ip,
+            target_ethernet_address: local_mac,
+            target_ip_address: local_ip,
+            ..Default::default()
+        };
+        *reply.payload_mut() = msg.serialize().unwrap().into();
+        assert!(iface.recv_frame(&reply).is_none());
+        assert_eq!(iface.frames_out().len(), 2);
+        for frame in iface.frames_out_mut().drain(..) {
+            assert_eq!(frame.header().dst, remote_mac);
+            assert_eq!(frame.header().ty, EthernetHeader::TYPE_IPV4);
+        }
+
+        // cached mapping expires after 30 s
+        iface.tick(29_999.into());
+        iface.send_datagram(&datagram(local_ip, remote_ip), &IPv4NUM(remote_ip).into());
+        assert_eq!(
+            iface.frames_out_mut().pop_front().unwrap().header().dst,
+            remote_mac
+        );
+        iface.tick(1.into());
+        iface.send_datagram(&datagram(local_ip, remote_ip), &IPv4NUM(remote_ip).into());
+        assert_eq!(
+            iface.frames_out_mut().pop_front().unwrap().header().ty,
+            EthernetHeader::TYPE_ARP
+        );
+    }
+
+    #[test]
+    fn test_reply_and_receive() {
+        let local_mac = EthernetAddress([0x02, 0, 0, 0, 0, 0x01]);
+        let remote_mac = EthernetAddress([0x02, 0, 0, 0, 0, 0x02]);
+        let local_ip = ip(10, 0, 0, 1);
+        let remote_ip = ip(10, 0, 0, 2);
+        let mut local = NetworkInterface::new(local_mac, IPv4NUM(local_ip).into());
+        let mut remote = NetworkInterface::new(remote_mac, IPv4NUM(remote_ip).into());
+
+        remote.send_datagram(&datagram(remote_ip, local_ip), &IPv4NUM(local_ip).into());
+        let request = remote.frames_out_mut().pop_front().unwrap();
+        assert!(local.recv_frame(&request).is_none());
+
+        let reply = local.frames_out_mut().pop_front().unwrap();
+        assert_eq!(reply.header().dst, remote_mac);
+        assert_eq!(arp_of(&reply).opcode, ARPMessage::OPCODE_REPLY);
+        assert!(remote.recv_frame(&reply).is_none());
+
+        let mut frame = remote.frames_out_mut().pop_front().unwrap();
+        let dgram = local.recv_frame(&frame).unwrap();
+        assert_eq!(dgram.header().src, remote_ip);
+        assert_eq!(Into::<Vec<u8>>::into(dgram.payload()), b"hello".to_vec());
+
+        // frames for another host are ignored
+        frame.header_mut().dst = EthernetAddress([0x02, 0, 0, 0, 0, 0x03]);
+        assert!(local.recv_frame(&frame).is_none());
+    }
 }
@@ -0,0 +1,227 @@
+use crate::{Address, EthernetFrame, IPv4NUM, InternetDatagram, NetworkInterface};
+
+use std::{
+    collections::VecDeque,
+    ops::{Deref, DerefMut},
+};
+
+pub struct AsyncNetworkInterface {
+    interface: NetworkInterface,
+    datagrams_out: VecDeque<InternetDatagram>,
+}
+
+impl From<NetworkInterface> for AsyncNetworkInterface {
+    fn from(interface: NetworkInterface) -> Self {
+        Self {
+            interface,
+            datagrams_out: VecDeque::new(),
+        }
+    }
+}
+
+impl Deref for AsyncNetworkInterface {
+    type Target = NetworkInterface;
+
+    fn deref(&self) -> &NetworkInterface {
+        &self.interface
+    }
+}
+
+impl DerefMut for AsyncNetworkInterface {
+    fn deref_mut(&mut self) -> &mut NetworkInterface {
+        &mut self.interface
+    }
+}
+
+impl AsyncNetworkInterface {
+    pub fn recv_frame(&mut self, frame: &EthernetFrame) {
+        if let Some(dgram) = self.interface.recv_frame(frame) {
+            self.datagrams_out.push_back(dgram);
+        }
+    }
+
+    #[inline(always)]
+    pub fn datagrams_out(&self) -> &VecDeque<InternetDatagram> {
+        &self.datagrams_out
+    }
+
+    #[inline(always)]
+    pub fn datagrams_out_mut(&mut self) -> &mut VecDeque<InternetDatagram> {
+        &mut self.datagrams_out
+    }
+}
+
+struct RouteEntry {
+    route_prefix: u32,
+    prefix_length: u8,
+    next_hop: Option<Address>,
+    interface_num: usize,
+}
+
+impl RouteEntry {
+    fn matches(&self, dst: u32) -> bool {
+        let mask = u32::MAX
+            .checked_shl(32 - self.prefix_length as u32)
+            .unwrap_or(0);
+        (dst ^ self.route_prefix) & mask == 0
+    }
+}
+
+#[derive(Default)]
+pub struct Router {
+    interfaces: Vec<AsyncNetworkInterface>,
+    routes: Vec<RouteEntry>,
+}
+
+impl Router {
+    fn route_one_datagram(&mut self, mut dgram: InternetDatagram) {
+        let dst = dgram.header().dst;
+        let Some(route) = self
+            .routes
+            .iter()
+            .filter(|route| route.matches(dst))
+            .max_by_key(|route| route.prefix_length)
+        else {
+            return;
+        };
+
+        if dgram.header().ttl <= 1 {
+            return;
+        }
+        dgram.header_mut().ttl -= 1;
+        if dgram.header_mut().recompute_cksum().is_err() {
+            return;
+        }
+
+        let interface = &mut self.interfaces[route.interface_num];
+        match &route.next_hop {
+            Some(next_hop) => interface.send_datagram(&dgram, next_hop),
+            None => interface.send_datagram(&dgram, &IPv4NUM(dst).into()),
+        }
+    }
+}
+
+impl Router {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add_interface(&mut self, interface: AsyncNetworkInterface) -> usize {
+        self.interfaces.push(interface);
+        self.interfaces.len() - 1
+    }
+
+    #[inline(always)]
+    pub fn interface(&self, n: usize) -> &AsyncNetworkInterface {
+        &self.interfaces[n]
+    }
+
+    #[inline(always)]
+    pub fn interface_mut(&mut self, n: usize) -> &mut AsyncNetworkInterface {
+        &mut self.interfaces[n]
+    }
+
+    pub fn add_route(
+        &mut self,
+        route_prefix: u32,
+        prefix_length: u8,
+        next_hop: Option<Address>,
+        interface_num: usize,
+    ) {
+        assert!(prefix_length <= 32, "prefix length must be at most 32");
+        assert!(
+            interface_num < self.interfaces.len(),
+            "route refers to an unknown interface"
+        );
+        self.routes.push(RouteEntry {
+            route_prefix,
+            prefix_length,
+            next_hop,
+            interface_num,
+        });
+    }
+
+    pub fn route(&mut self) {
+        for i in 0..self.interfaces.len() {
+            while let Some(dgram) = self.interfaces[i].datagrams_out_mut().pop_front() {
+                self.route_one_datagram(dgram);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ARPMessage, Buffer, EthernetAddress, EthernetHeader};
+
+    fn ip(a: u8, b: u8, c: u8, d: u8) -> u32 {
+        u32::from_be_bytes([a, b, c, d])
+    }
+
+    fn router_with(n: usize) -> Router {
+        let mut router = Router::new();
+        for i in 0..n {
+            let mac = EthernetAddress([0x02, 0, 0, 0, 0, i as u8]);
+            let addr = IPv4NUM(ip(10, i as u8, 0, 1)).into();
+            router.add_interface(NetworkInterface::new(mac, addr).into());
+        }
+        router
+    }
+
+    fn datagram(dst: u32, ttl: u8) -> InternetDatagram {
+        let mut dgram = InternetDatagram::default();
+        dgram.header_mut().src = ip(192, 168, 0, 1);
+        dgram.header_mut().dst = dst;
+        dgram.header_mut().ttl = ttl;
+        dgram.header_mut().len = 20;
+        dgram
+    }
+
+    fn arp_target(router: &mut Router, n: usize) -> Option<u32> {
+        let frame = router.interface_mut(n).frames_out_mut().pop_front()?;
+        assert_eq!(frame.header().ty, EthernetHeader::TYPE_ARP);
+        let mut msg = ARPMessage::default();
+        msg.parse(Buffer::from(Into::<Vec<u8>>::into(frame.payload())))
+            .unwrap();
+        Some(msg.target_ip_address)
+    }
+
+    #[test]
+    fn test_longest_prefix_match() {
+        let mut router = router_with(3);
+        router.add_route(0, 0, Some(IPv4NUM(ip(10, 0, 0, 254)).into()), 0);
+        router.add_route(ip(172, 16, 0, 0), 12, None, 1);
+        router.add_route(ip(172, 16, 5, 0), 24, None, 2);
+
+        router
+            .interface_mut(0)
+            .datagrams_out_mut()
+            .push_back(datagram(ip(172, 16, 5, 9), 64));
+        router
+            .interface_mut(0)
+            .datagrams_out_mut()
+            .push_back(datagram(ip(172, 17, 0, 1), 64));
+        router
+            .interface_mut(1)
+            .datagrams_out_mut()
+            .push_back(datagram(ip(8, 8, 8, 8), 64));
+        router.route();
+
+        assert_eq!(arp_target(&mut router, 2), Some(ip(172, 16, 5, 9)));
+        assert_eq!(arp_target(&mut router, 1), Some(ip(172, 17, 0, 1)));
+        assert_eq!(arp_target(&mut router, 0), Some(ip(10, 0, 0, 254)));
+    }
+
+    #[test]
+    fn test_ttl_expired() {
+        let mut router = router_with(1);
+        router.add_route(0, 0, None, 0);
+        router
+            .interface_mut(0)
+            .datagrams_out_mut()
+            .push_back(datagram(ip(1, 2, 3, 4), 1));
+        router.route();
+        assert!(router.interface(0).frames_out().is_empty());
+    }
+}
@@ -30,7 +30,7 @@ impl BlockNode {
     }
 
     pub fn can_merge(&self, other: &Self) -> bool {
-        self.begin + self.len() >= other.begin || self.begin <= other.begin + other.len()
+        self.begin <= other.begin + other.len() && other.begin <= self.begin + self.len()
     }
 
     pub fn merge(self, other: Self) -> Self {
@@ -51,7 +51,8 @@ impl BlockNode {
     fn merge_impl(mut self, other: Self) -> Self {
         let self_end = self.begin + self.len();
         if self_end < other.begin + other.len() {
-            self.data.extend_from_slice(&other.data[self_end..])
+            self.data
+                .extend_from_slice(&other.data[(self_end - other.begin)..])
         }
         self
     }
@@ -60,20 +61,17 @@ impl BlockNode {
 #[derive(Debug, Default)]
 pub struct StreamReassembler {
     pending_blocks: BTreeMap<usize, BlockNode>,
-    buffer: Vec<u8>,
     unassemble_bytes: usize,
     head_index: usize,
     eof_flag: bool,
+    eof_index: usize,
     output: ByteStream,
     capacity: usize,
 }
 
 impl StreamReassembler {
-    fn judge_eof(&mut self, eof: bool) {
-        if eof {
-            self.eof_flag = true;
-        }
-        if self.eof_flag && self.buffer.is_empty() {
+    fn judge_eof(&mut self) {
+        if self.eof_flag && self.head_index == self.eof_index {
             self.output.end_input();
         }
     }
@@ -82,27 +80,24 @@ impl StreamReassembler {
         StreamReassembler {
             capacity,
             output: ByteStream::new(capacity),
-            buffer: Vec::with_capacity(capacity),
             ..Default::default()
         }
     }
 
     fn try_get_next_merge(&self, new_node: &BlockNode) -> Option<usize> {
-        for (idx, node) in self.pending_blocks.range(new_node.begin..) {
-            if new_node.can_merge(node) {
-                return Some(*idx);
-            }
-        }
-        None
+        self.pending_blocks
+            .range(new_node.begin..)
+            .next()
+            .filter(|(_, node)| new_node.can_merge(node))
+            .map(|(idx, _)| *idx)
     }
 
     fn try_get_prev_merge(&self, new_node: &BlockNode) -> Option<usize> {
-        for (idx, node) in self.pending_blocks.range(..=new_node.begin).rev() {
-            if new_node.can_merge(node) {
-                return Some(*idx);
-            }
-        }
-        None
+        self.pending_blocks
+            .range(..=new_node.begin)
+            .next_back()
+            .filter(|(_, node)| new_node.can_merge(node))
+            .map(|(idx, _)| *idx)
     }
 
     fn remove_pending(&mut self, index: usize) -> BlockNode {
@@ -117,40 +112,41 @@ impl StreamReassembler {
     }
 
     pub fn push_substring(&mut self, data: &[u8], index: usize, eof: bool) {
-        if index >= self.head_index + self.capacity {
-            return;
+        let first_unacceptable = self.head_index + self.capacity - self.output.buffer_size();
+        if eof && index + data.len() <= first_unacceptable {
+            self.eof_flag = true;
+            self.eof_index = index + data.len();
         }
 
-        let mut new_node;
-        if index + data.len() <= self.head_index {
-            return self.judge_eof(eof);
-        } else if index < self.head_index {
-            new_node = BlockNode::new(self.head_index, &data[(self.head_index - index)..]);
-        } else {
-            new_node = BlockNode::new(index, data);
-        }
+        let begin = index.max(self.head_index);
+        let end = (index + data.len()).min(first_unacceptable);
+        if begin < end {
+            let mut new_node = BlockNode::new(begin, &data[(begin - index)..(end - index)]);
 
-        // merge next
-        while let Some(idx) = self.try_get_next_merge(&new_node) {
-            let node_to_merge = self.remove_pending(idx);
-            new_node = new_node.merge(node_to_merge);
-        }
+            // merge prev
+            while let Some(idx) = self.try_get_prev_merge(&new_node) {
+                let node_to_merge = self.remove_pending(idx);
+                new_node = new_node.merge(node_to_merge);
+            }
 
-        // merge prev
-        while let Some(idx) = self.try_get_prev_merge(&new_node) {
-            let node_to_merge = self.remove_pending(idx);
-            new_node = new_node.merge(node_to_merge);
-        }
+            // merge next
+            while let Some(idx) = self.try_get_next_merge(&new_node) {
+                let node_to_merge = self.remove_pending(idx);
+                new_node = new_node.merge(node_to_merge);
+            }
 
-        self.insert_pending(new_node);
+            self.insert_pending(new_node);
+        }
 
         // write to ByteStream
-        if let Some((&idx, head)) = self.pending_blocks.first_key_value() {
-            if head.begin == self.head_index {
-                let head = self.remove_pending(idx);
-                self.head_index += self.output.write(&head.data);
-            }
+        if let Some((&idx, head)) = self.pending_blocks.first_key_value()
+            && head.begin == self.head_index
+        {
+            let head = self.remove_pending(idx);
+            self.head_index += self.output.write(&head.data);
         }
+
+        self.judge_eof();
     }
 
     #[inline(always)]
@@ -180,6 +176,89 @@ impl StreamReassembler {
 
     #[inline(always)]
     pub fn input_ended(&self) -> bool {
-        false
+        self.output.input_ended()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn read_all(r: &mut StreamReassembler) -> Vec<u8> {
+        let out = r.stream_out_mut();
+        out.read(out.buffer_size())
+    }
+
+    #[test]
+    fn test_out_of_order() {
+        let mut r = StreamReassembler::new(65000);
+        r.push_substring(b"efgh", 4, false);
+        r.push_substring(b"mnop", 12, false);
+        assert_eq!(r.unassemble_bytes(), 8);
+        assert_eq!(read_all(&mut r), b"");
+
+        r.push_substring(b"abcd", 0, false);
+        assert_eq!(read_all(&mut r), b"abcdefgh");
+        assert_eq!(r.head_index(), 8);
+        assert_eq!(r.unassemble_bytes(), 4);
+
+        r.push_substring(b"ijkl", 8, false);
+        assert_eq!(read_all(&mut r), b"ijklmnop");
+        assert!(r.is_empty());
+        assert!(!r.input_ended());
+
+        r.push_substring(b"", 16, true);
+        assert!(r.input_ended());
+    }
+
+    #[test]
+    fn test_overlapping() {
+        let mut r = StreamReassembler::new(65000);
+        // disjoint blocks stay apart
+        r.push_substring(b"c", 2, false);
+        r.push_substring(b"gh", 6, false);
+        assert_eq!(r.unassemble_bytes(), 3);
+
+        // bridges both blocks, keeping the bytes past each overlap
+        r.push_substring(b"bcdefg", 1, false);
+        assert_eq!(r.unassemble_bytes(), 7);
+        r.push_substring(b"ab", 0, false);
+        assert_eq!(read_all(&mut r), b"abcdefgh");
+
+        r.push_substring(b"efghij", 4, false);
+        assert_eq!(read_all(&mut r), b"ij");
+        r.push_substring(b"abc", 0, false);
+        assert_eq!(read_all(&mut r), b"");
+        assert!(r.is_empty());
+    }
+
+    #[test]
+    fn test_capacity() {
+        let mut r = StreamReassembler::new(8);
+        r.push_substring(b"abcdef", 0, false);
+        // only two more bytes fit until the output is read
+        r.push_substring(b"ghijkl", 6, true);
+        assert_eq!(r.head_index(), 8);
+        assert!(!r.input_ended());
+        assert_eq!(read_all(&mut r), b"abcdefgh");
+
+        r.push_substring(b"ghijkl", 6, true);
+        assert_eq!(read_all(&mut r), b"ijkl");
+        assert!(r.input_ended());
+        assert!(r.stream_out().eof());
+    }
+
+    #[test]
+    fn test_eof_before_data() {
+        let mut r = StreamReassembler::new(65000);
+        r.push_substring(b"", 3, true);
+        assert!(!r.input_ended());
+        r.push_substring(b"abc", 0, false);
+        assert_eq!(read_all(&mut r), b"abc");
+        assert!(r.input_ended());
+
+        let mut r = StreamReassembler::new(65000);
+        r.push_substring(b"", 0, true);
+        assert!(r.stream_out().eof());
     }
 }
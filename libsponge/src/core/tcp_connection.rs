@@ -19,6 +19,8 @@ pub struct TCPConnection {
 
 impl TCPConnection {
     fn set_rst(&mut self) {
+        self.sender.stream_in_mut().set_error();
+        self.receiver.stream_out_mut().set_error();
         self.sender
             .set_state(Err(Error::from(TCPConnectionError::SenderError)));
         self.receiver
@@ -100,7 +102,7 @@ impl TCPConnection {
     }
 
     pub fn write(&mut self, data: &[u8]) -> usize {
-        if data.len() == 0 {
+        if data.is_empty() {
             return 0;
         }
         let ret = self.sender.stream_in_mut().write(data);
@@ -120,6 +122,11 @@ impl TCPConnection {
         self.real_send();
     }
 
+    #[inline(always)]
+    pub fn inbound_stream(&self) -> &ByteStream {
+        self.receiver.stream_out()
+    }
+
     #[inline(always)]
     pub fn inbound_stream_mut(&mut self) -> &mut ByteStream {
         self.receiver.stream_out_mut()
@@ -171,6 +178,12 @@ impl TCPConnection {
 
     // TODO: rewrite this function based on PSM?
     pub fn segment_received(&mut self, seg: &TCPSegment) {
+        if !self.active {
+            return;
+        }
+        if self.receiver.ackno().is_none() && !seg.header().syn {
+            return;
+        }
         self.ms_since_last_seg_recv = 0.into();
         if seg.header().rst {
             self.set_rst();
@@ -182,8 +195,11 @@ impl TCPConnection {
         self.linger_mut();
 
         if seg.header().ack {
-            self.sender
-                .ack_received(&seg.header().ack_no, seg.header().win);
+            self.sender.ack_received(
+                &seg.header().ack_no,
+                seg.header().win,
+                seg.length_in_sequence_space() > 0,
+            );
             self.real_send();
         }
 
@@ -196,6 +212,8 @@ impl TCPConnection {
                 self.segments_out.push_back(ack_seg);
             }
         }
+
+        self.active_mut();
     }
 
     pub fn tick(&mut self, ms_since_last_tick: Milliseconds) {
@@ -214,11 +232,24 @@ impl TCPConnection {
             }
             self.segments_out_mut().push_back(retx_seg);
         }
+        // whatever pacing let out since the last tick
+        if self.active {
+            self.real_send();
+        }
 
         self.linger_mut();
         self.active_mut();
     }
 
+    pub fn reset(&mut self) {
+        self.set_rst();
+        self.send_rst();
+    }
+
+    pub fn segments_out(&self) -> &VecDeque<TCPSegment> {
+        &self.segments_out
+    }
+
     pub fn segments_out_mut(&mut self) -> &mut VecDeque<TCPSegment> {
         &mut self.segments_out
     }
@@ -232,8 +263,139 @@ impl TCPConnection {
 impl Drop for TCPConnection {
     fn drop(&mut self) {
         if self.active {
-            self.set_rst();
-            self.send_rst();
+            self.reset();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::CongestionControlKind;
+
+    fn exchange(a: &mut TCPConnection, b: &mut TCPConnection) -> bool {
+        let mut moved = false;
+        while let Some(seg) = a.segments_out_mut().pop_front() {
+            b.segment_received(&seg);
+            moved = true;
+        }
+        while let Some(seg) = b.segments_out_mut().pop_front() {
+            a.segment_received(&seg);
+            moved = true;
+        }
+        moved
+    }
+
+    #[test]
+    fn test_handshake_transfer_and_close() {
+        let cfg = TCPConfig::default();
+        let mut client = TCPConnection::with_config(&cfg);
+        let mut server = TCPConnection::with_config(&cfg);
+
+        client.connect();
+        assert!(matches!(client.renew_state(), Ok(TCPState::SynSent)));
+        while exchange(&mut client, &mut server) {}
+        assert!(matches!(client.renew_state(), Ok(TCPState::Established)));
+        assert!(matches!(server.renew_state(), Ok(TCPState::Established)));
+
+        let data: Vec<u8> = (0..100_000).map(|i| i as u8).collect();
+        let mut written = 0;
+        let mut received = Vec::new();
+        while received.len() < data.len() {
+            written += client.write(&data[written..]);
+            while exchange(&mut client, &mut server) {}
+            let inbound = server.inbound_stream_mut();
+            received.extend(inbound.read(inbound.buffer_size()));
+        }
+        assert_eq!(received, data);
+
+        client.end_input_stream();
+        while exchange(&mut client, &mut server) {}
+        assert!(matches!(client.renew_state(), Ok(TCPState::FinWait2)));
+        assert!(matches!(server.renew_state(), Ok(TCPState::CloseWait)));
+        assert!(server.inbound_stream().eof());
+
+        server.end_input_stream();
+        while exchange(&mut client, &mut server) {}
+        assert!(matches!(client.renew_state(), Ok(TCPState::TimeWait)));
+        assert!(!server.active());
+
+        client.tick((cfg.rt_timeout as u64 * 10).into());
+        assert!(!client.active());
+    }
+
+    #[test]
+    fn test_retransmission_after_loss() {
+        let cfg = TCPConfig::default();
+        let mut client = TCPConnection::with_config(&cfg);
+        let mut server = TCPConnection::with_config(&cfg);
+
+        client.connect();
+        client.segments_out_mut().clear();
+        client.tick((cfg.timeout_default as u64).into());
+        assert_eq!(client.segments_out_mut().len(), 1);
+        while exchange(&mut client, &mut server) {}
+        assert!(matches!(client.renew_state(), Ok(TCPState::Established)));
+
+        client.write(b"lost");
+        client.segments_out_mut().clear();
+        client.tick((cfg.timeout_default as u64).into());
+        while exchange(&mut client, &mut server) {}
+        assert_eq!(server.inbound_stream_mut().read(4), b"lost".to_vec());
+        assert_eq!(client.bytes_in_flight(), 0);
+    }
+
+    #[test]
+    fn test_ignores_segments_before_syn_and_after_rst() {
+        let cfg = TCPConfig::default();
+        let mut client = TCPConnection::with_config(&cfg);
+        let mut server = TCPConnection::with_config(&cfg);
+
+        // a stray ACK to a listening server is dropped
+        let mut stray = TCPSegment::default();
+        stray.header_mut().ack = true;
+        server.segment_received(&stray);
+        assert!(server.segments_out().is_empty());
+        assert!(matches!(server.renew_state(), Ok(TCPState::Listen)));
+
+        client.connect();
+        while exchange(&mut client, &mut server) {}
+        client.reset();
+        assert!(client.segments_out().back().unwrap().header().rst);
+        while exchange(&mut client, &mut server) {}
+        assert!(!server.active());
+        assert!(server.inbound_stream().error());
+
+        // a reset connection no longer answers
+        server.segment_received(&stray);
+        assert!(server.segments_out().is_empty());
+    }
+
+    #[test]
+    fn test_window_limited_by_cwnd() {
+        // the byte the ACK of the SYN grew the initial window by isn't worth a segment
+        for (kind, flight) in [
+            (
+                CongestionControlKind::NewReno,
+                3 * TCPConfig::MAX_PAYLOAD_SIZE,
+            ),
+            (
+                CongestionControlKind::Cubic,
+                3 * TCPConfig::MAX_PAYLOAD_SIZE,
+            ),
+            (CongestionControlKind::None, TCPConfig::DEFAULT_CAPACITY),
+        ] {
+            let cfg = TCPConfig {
+                congestion_control: kind,
+                ..Default::default()
+            };
+            let mut client = TCPConnection::with_config(&cfg);
+            let mut server = TCPConnection::with_config(&cfg);
+            client.connect();
+            while exchange(&mut client, &mut server) {}
+
+            client.write(&vec![0; cfg.send_capacity]);
+            assert_eq!(client.bytes_in_flight(), flight, "{:?}", kind);
         }
     }
 }
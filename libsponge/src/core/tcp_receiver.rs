@@ -34,10 +34,12 @@ impl TCPReceiver {
 
     pub fn ackno(&self) -> Option<WrappingU32> {
         let idx = self.reassembler.head_index() as u64;
-        match (&self.state, self.reassembler.is_empty()) {
-            (Ok(ReceiverState::Listen), _) => None,
-            (Ok(ReceiverState::FinRcvd), true) => Some(WrappingU32::wrap(idx + 2, &self.isn)),
-            _ => Some(WrappingU32::wrap(idx + 1, &self.isn)),
+        match &self.state {
+            Ok(ReceiverState::Listen) => None,
+            _ => Some(WrappingU32::wrap(
+                idx + 1 + self.stream_out().input_ended() as u64,
+                &self.isn,
+            )),
         }
     }
 
@@ -53,30 +55,27 @@ impl TCPReceiver {
 
     pub fn segment_received(&mut self, seg: &TCPSegment) {
         let header = seg.header();
-        match (header.syn, header.fin, &self.state) {
-            (true, fin, Ok(ReceiverState::Listen)) => {
-                self.set_state(Ok(ReceiverState::SynRcvd));
-                self.isn = header.seq_no.clone();
-                if fin {
-                    self.set_state(Ok(ReceiverState::FinRcvd));
-                }
-                self.reassembler
-                    .push_substring(seg.payload().as_ref(), 0, fin);
+        if let Ok(ReceiverState::Listen) = self.state {
+            if !header.syn {
                 return;
             }
-            (_, true, Ok(ReceiverState::SynRcvd)) => {
-                self.set_state(Ok(ReceiverState::FinRcvd));
-            }
-            _ => {}
+            self.set_state(Ok(ReceiverState::SynRcvd));
+            self.isn = header.seq_no.clone();
         }
+
         let check_point = self.reassembler.head_index();
-        let mut abs_seqno = WrappingU32::unwrap(&header.seq_no, &self.isn, check_point as _);
-        match self.state {
-            Ok(ReceiverState::SynRcvd) => abs_seqno -= 1,
-            _ => {}
-        }
+        let abs_seqno = WrappingU32::unwrap(&header.seq_no, &self.isn, check_point as _);
+        let stream_index = match (header.syn, abs_seqno.checked_sub(1)) {
+            (true, _) => 0,
+            (false, Some(idx)) => idx,
+            (false, None) => return,
+        };
         self.reassembler
-            .push_substring(seg.payload().as_ref(), abs_seqno as _, header.fin);
+            .push_substring(seg.payload().as_ref(), stream_index as _, header.fin);
+
+        if self.stream_out().input_ended() {
+            self.set_state(Ok(ReceiverState::FinRcvd));
+        }
     }
 
     #[inline(always)]
@@ -116,3 +115,69 @@ impl TCPReceiver {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Buffer;
+
+    fn segment(seqno: u32, syn: bool, fin: bool, payload: &[u8]) -> TCPSegment {
+        let mut seg = TCPSegment::default();
+        let header = seg.header_mut();
+        header.seq_no = seqno.into();
+        header.syn = syn;
+        header.fin = fin;
+        *seg.payload_mut() = Buffer::from(payload.to_vec());
+        seg
+    }
+
+    fn read_all(r: &mut TCPReceiver) -> Vec<u8> {
+        let out = r.stream_out_mut();
+        out.read(out.buffer_size())
+    }
+
+    #[test]
+    fn test_data_before_syn_is_dropped() {
+        let mut r = TCPReceiver::new(4000);
+        r.segment_received(&segment(1, false, false, b"abcd"));
+        assert!(r.ackno().is_none());
+        assert_eq!(r.unassembled_bytes(), 0);
+
+        r.segment_received(&segment(u32::MAX, true, false, b""));
+        assert_eq!(r.ackno(), Some(0.into()));
+        // data can't sit on the SYN's own seqno
+        r.segment_received(&segment(u32::MAX, false, false, b"zz"));
+        assert_eq!(r.ackno(), Some(0.into()));
+        r.segment_received(&segment(0, false, false, b"abcd"));
+        assert_eq!(r.ackno(), Some(4.into()));
+        assert_eq!(read_all(&mut r), b"abcd");
+    }
+
+    #[test]
+    fn test_out_of_order_and_fin() {
+        let mut r = TCPReceiver::new(4000);
+        r.segment_received(&segment(100, true, false, b"ab"));
+        assert_eq!(r.ackno(), Some(103.into()));
+
+        // the FIN arrives before the bytes in front of it
+        r.segment_received(&segment(105, false, true, b"ef"));
+        assert_eq!(r.ackno(), Some(103.into()));
+        assert_eq!(r.unassembled_bytes(), 2);
+        assert!(matches!(r.state(), Ok(ReceiverState::SynRcvd)));
+
+        r.segment_received(&segment(103, false, false, b"cd"));
+        assert_eq!(r.ackno(), Some(108.into()));
+        assert!(matches!(r.state(), Ok(ReceiverState::FinRcvd)));
+        assert_eq!(read_all(&mut r), b"abcdef");
+        assert!(r.stream_out().eof());
+    }
+
+    #[test]
+    fn test_syn_with_fin() {
+        let mut r = TCPReceiver::new(4000);
+        r.segment_received(&segment(0, true, true, b"hi"));
+        assert_eq!(r.ackno(), Some(4.into()));
+        assert!(r.stream_out().input_ended());
+        assert_eq!(r.win_size(), 3998);
+    }
+}
@@ -1,5 +1,6 @@
 use crate::{
-    Buffer, ByteStream, Milliseconds, SenderState, TCPConfig, TCPConnectionError, TCPSegment,
+    AckEvent, Buffer, ByteStream, CongestionControl, LossEvent, Milliseconds,
+    NoneCongestionControl, RateSample, SenderState, TCPConfig, TCPConnectionError, TCPSegment,
     WrappingU32,
 };
 
@@ -7,22 +8,80 @@ use anyhow::{Error, Result};
 
 use std::collections::VecDeque;
 
+struct OutstandingSegment {
+    seg: TCPSegment,
+    sent_at: Milliseconds,
+    retransmitted: bool,
+    // the sender's delivery counters when the segment went out
+    delivered: u64,
+    delivered_at: Milliseconds,
+    first_sent_at: Milliseconds,
+}
+
+// RFC 6298 smoothed round-trip time, in ms
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RTTEstimate {
+    pub srtt: f64,
+    pub rttvar: f64,
+    pub latest: Milliseconds,
+}
+
+impl RTTEstimate {
+    fn new(rtt: Milliseconds) -> Self {
+        let r = u64::from(rtt) as f64;
+        Self {
+            srtt: r,
+            rttvar: r / 2.0,
+            latest: rtt,
+        }
+    }
+
+    fn update(&mut self, rtt: Milliseconds) {
+        let r = u64::from(rtt) as f64;
+        self.rttvar = 0.75 * self.rttvar + 0.25 * (self.srtt - r).abs();
+        self.srtt = 0.875 * self.srtt + 0.125 * r;
+        self.latest = rtt;
+    }
+
+    // with the clock granularity of a 1 ms tick as the floor for the variance term
+    pub fn rto(&self) -> Milliseconds {
+        ((self.srtt + (4.0 * self.rttvar).max(1.0)).ceil() as u64).into()
+    }
+}
+
 // #[derive(Default)]
 pub struct TCPSender {
     isn: WrappingU32,
     pub segments_out: VecDeque<TCPSegment>,
-    initial_retx_timeout: Milliseconds,
+    // the RTO before any backoff
+    rto: Milliseconds,
+    min_rto: Milliseconds,
+    max_rto: Milliseconds,
+    rtt: Option<RTTEstimate>,
     stream_in: ByteStream,
     next_seqno: u64,
-    segments_outstanding: VecDeque<TCPSegment>,
+    segments_outstanding: VecDeque<OutstandingSegment>,
     bytes_in_flight: usize,
     receiver_window_size: u16,
-    receiver_free_space: u16,
+    recv_ackno: u64,
     timer: Milliseconds,
     timer_running: bool,
     retx_timeout: Milliseconds,
     consq_retxs: usize,
+    dup_acks: usize,
+    // next_seqno when fast recovery or the last timeout began
+    recover: Option<u64>,
+    fast_retransmits: usize,
     state: Result<SenderState>,
+    congestion: Box<dyn CongestionControl>,
+    // time as seen through tick()
+    now: Milliseconds,
+    // bytes acked so far, when that last grew, and when the segment behind it was sent
+    delivered: u64,
+    delivered_at: Milliseconds,
+    first_sent_at: Milliseconds,
+    // bytes the pacing rate still allows out before the next tick
+    pacing_credit: f64,
 }
 
 impl Default for TCPSender {
@@ -30,46 +89,61 @@ impl Default for TCPSender {
         Self {
             isn: WrappingU32::default(),
             segments_out: VecDeque::new(),
-            initial_retx_timeout: Milliseconds::default(),
+            rto: Milliseconds::default(),
+            min_rto: Milliseconds::default(),
+            max_rto: Milliseconds::default(),
+            rtt: None,
             stream_in: ByteStream::default(),
             next_seqno: 0,
             segments_outstanding: VecDeque::new(),
             bytes_in_flight: 0,
             receiver_window_size: 0,
-            receiver_free_space: 0,
+            recv_ackno: 0,
             timer: Milliseconds::default(),
             timer_running: false,
             retx_timeout: Milliseconds::default(),
             consq_retxs: 0,
+            dup_acks: 0,
+            recover: None,
+            fast_retransmits: 0,
             state: Ok(SenderState::Closed),
+            congestion: Box::new(NoneCongestionControl),
+            now: Milliseconds::default(),
+            delivered: 0,
+            delivered_at: Milliseconds::default(),
+            first_sent_at: Milliseconds::default(),
+            pacing_credit: 0.0,
         }
     }
 }
 
 impl TCPSender {
     fn ack_is_valid(&self, abs_ackno: usize) -> bool {
-        abs_ackno <= self.next_seqno as usize
-            && match self.segments_outstanding.front() {
-                Some(seg) => {
-                    abs_ackno
-                        >= WrappingU32::unwrap(&seg.header().seq_no, &self.isn, self.next_seqno)
-                            as _
-                }
-                None => true,
-            }
+        abs_ackno <= self.next_seqno as usize && abs_ackno >= self.recv_ackno as usize
+    }
+
+    fn fin_sent(&self) -> bool {
+        self.stream_in.eof() && self.next_seqno == self.stream_in.bytes_written() as u64 + 2
     }
 
     fn send_segment(&mut self, mut seg: TCPSegment) {
         seg.header_mut().seq_no = WrappingU32::wrap(self.next_seqno, &self.isn);
-        self.next_seqno += seg.length_in_sequence_space() as u64;
-        self.bytes_in_flight += seg.length_in_sequence_space() as usize;
-        match self.state {
-            Ok(SenderState::SynSent) | Ok(SenderState::SynAcked) => {
-                self.receiver_free_space -= seg.length_in_sequence_space() as u16
-            }
-            _ => {}
+        if self.bytes_in_flight == 0 {
+            // nothing to measure across an idle period
+            self.first_sent_at = self.now;
+            self.delivered_at = self.now;
         }
-        self.segments_outstanding.push_back(seg.clone());
+        self.next_seqno += seg.length_in_sequence_space() as u64;
+        self.bytes_in_flight += seg.length_in_sequence_space();
+        self.pacing_credit -= seg.length_in_sequence_space() as f64;
+        self.segments_outstanding.push_back(OutstandingSegment {
+            seg: seg.clone(),
+            sent_at: self.now,
+            retransmitted: false,
+            delivered: self.delivered,
+            delivered_at: self.delivered_at,
+            first_sent_at: self.first_sent_at,
+        });
         self.segments_out.push_back(seg);
         if !self.timer_running {
             self.timer_running = true;
@@ -78,35 +152,79 @@ impl TCPSender {
     }
 
     pub fn with_config(cfg: &TCPConfig) -> Self {
-        let isn = cfg
-            .fixed_isn
-            .clone()
-            .unwrap_or_else(|| WrappingU32::random());
-        let timeout = (cfg.timeout_default as u64).into();
-        Self {
+        let isn = cfg.fixed_isn.clone().unwrap_or_else(WrappingU32::random);
+        let mut sender = Self {
             isn,
-            initial_retx_timeout: timeout,
-            retx_timeout: timeout,
+            min_rto: (cfg.min_rto as u64).into(),
+            max_rto: (cfg.max_rto as u64).into(),
             stream_in: ByteStream::new(cfg.send_capacity),
+            congestion: cfg
+                .congestion_control
+                .build(TCPConfig::MAX_PAYLOAD_SIZE, cfg.rng_seed),
             ..Default::default()
+        };
+        // the bounds hold before the first RTT sample too
+        sender.rto = sender.bound_rto((cfg.timeout_default as u64).into());
+        sender.retx_timeout = sender.rto;
+        sender
+    }
+
+    // unlike clamp, a minimum above the maximum doesn't panic: the maximum wins
+    fn bound_rto(&self, rto: Milliseconds) -> Milliseconds {
+        rto.max(self.min_rto).min(self.max_rto)
+    }
+
+    fn update_rto(&mut self, sample: Milliseconds) {
+        match &mut self.rtt {
+            Some(rtt) => rtt.update(sample),
+            None => self.rtt = Some(RTTEstimate::new(sample)),
         }
+        self.rto = self.bound_rto(self.rtt.unwrap().rto());
     }
 
-    pub fn tick(&mut self, ms_since_last_tick: Milliseconds) {
-        if !self.timer_running {
-            return;
+    fn retransmit_first(&mut self) -> &TCPSegment {
+        let outstanding = self.segments_outstanding.front_mut().unwrap();
+        outstanding.retransmitted = true;
+        self.segments_out.push_back(outstanding.seg.clone());
+        self.segments_out.back().unwrap()
+    }
+
+    fn loss_event(&self) -> LossEvent {
+        LossEvent {
+            next_seqno: self.next_seqno,
+            bytes_in_flight: self.bytes_in_flight,
+            now: self.now,
         }
-        self.timer += ms_since_last_tick;
-        if self.timer >= self.retx_timeout {
-            self.segments_out
-                .push_back(self.segments_outstanding.front().unwrap().clone());
-            if self.receiver_window_size > 0
-                || self.segments_outstanding.front().unwrap().header().syn
-            {
-                self.consq_retxs += 1;
-                self.retx_timeout <<= 1;
+    }
+
+    pub fn tick(&mut self, ms_since_last_tick: Milliseconds) {
+        self.now += ms_since_last_tick;
+        if self.timer_running {
+            self.timer += ms_since_last_tick;
+            if self.timer >= self.retx_timeout {
+                let syn = self.retransmit_first().header().syn;
+                // a zero-window probe going unanswered is no sign of congestion
+                if self.receiver_window_size > 0 || syn {
+                    self.consq_retxs += 1;
+                    self.retx_timeout <<= 1;
+                    self.retx_timeout = self.retx_timeout.min(self.max_rto);
+                    let loss = self.loss_event();
+                    self.congestion.on_timeout(&loss);
+                    // everything sent so far is suspect: repair it hole by hole as ACKs come
+                    // back, and don't take the duplicates of that for a new loss
+                    self.dup_acks = 0;
+                    self.recover = Some(self.next_seqno);
+                }
+                self.timer = 0.into();
             }
         }
+        if let Some(rate) = self.congestion.pacing_rate() {
+            // credit doesn't pile up beyond one tick's worth, or a couple of segments
+            let refill = rate * u64::from(ms_since_last_tick) as f64;
+            let burst = refill.max(2.0 * TCPConfig::MAX_PAYLOAD_SIZE as f64);
+            self.pacing_credit = (self.pacing_credit + refill).min(burst);
+            self.fill_window();
+        }
     }
 
     pub fn stream_in(&self) -> &ByteStream {
@@ -142,45 +260,116 @@ impl TCPSender {
             (_, 0, _, _, _) => self.set_state(Ok(SenderState::Closed)),
             (_, i, _, j, _) if i == j => self.set_state(Ok(SenderState::SynSent)),
             (_, _, false, _, _) => self.set_state(Ok(SenderState::SynAcked)),
-            (_, _, _, i, j) if i < j + 2 => self.set_state(Ok(SenderState::SynAcked)),
+            (_, i, _, _, j) if i < j + 2 => self.set_state(Ok(SenderState::SynAcked)),
             (_, _, _, i, _) if i != 0 => self.set_state(Ok(SenderState::FinSent)),
             _ => self.set_state(Ok(SenderState::FinAcked)),
         }
         &self.state
     }
 
-    pub fn ack_received(&mut self, ackno: &WrappingU32, window_size: u16) {
+    pub fn ack_received(&mut self, ackno: &WrappingU32, window_size: u16, carries_data: bool) {
         let abs_ackno = WrappingU32::unwrap(ackno, &self.isn, self.next_seqno as _);
         if !self.ack_is_valid(abs_ackno as _) {
             return;
         }
 
+        let acked_bytes = (abs_ackno - self.recv_ackno) as usize;
+        // RFC 5681: acks nothing new, carries nothing, and leaves the (open) window alone
+        let duplicate = acked_bytes == 0
+            && !carries_data
+            && window_size == self.receiver_window_size
+            && window_size > 0
+            && self.bytes_in_flight > 0;
         self.receiver_window_size = window_size;
-        self.receiver_free_space = window_size;
+        self.recv_ackno = abs_ackno;
 
-        while let Some(seg) = self.segments_outstanding.front() {
+        // the controller hears about new data and duplicates, except the one that signals loss
+        let mut notify = acked_bytes > 0 || duplicate;
+        if duplicate {
+            self.dup_acks += 1;
+            if self.dup_acks == 3 && self.recover.is_none() {
+                self.fast_retransmits += 1;
+                self.recover = Some(self.next_seqno);
+                let loss = self.loss_event();
+                self.congestion.on_loss(&loss);
+                self.retransmit_first();
+                notify = false;
+            }
+        } else if acked_bytes > 0 {
+            self.dup_acks = 0;
+        }
+
+        if acked_bytes > 0 {
+            self.delivered += acked_bytes as u64;
+            self.delivered_at = self.now;
+        }
+
+        // Karn: only segments sent exactly once give an unambiguous RTT or rate sample
+        let mut rtt = None;
+        let mut rate = None;
+        let mut retransmission_acked = false;
+        while let Some(outstanding) = self.segments_outstanding.front() {
+            let seg = &outstanding.seg;
             if WrappingU32::unwrap(&seg.header().seq_no, &self.isn, self.next_seqno)
                 + seg.length_in_sequence_space() as u64
                 <= abs_ackno
             {
+                if !outstanding.retransmitted {
+                    rtt = Some(self.now - outstanding.sent_at);
+                    // the slower of the send and ACK rates over the segment's flight
+                    let send_elapsed = outstanding.sent_at - outstanding.first_sent_at;
+                    let ack_elapsed = self.delivered_at - outstanding.delivered_at;
+                    rate = Some(RateSample {
+                        delivered: (self.delivered - outstanding.delivered) as usize,
+                        interval: send_elapsed.max(ack_elapsed),
+                    });
+                    self.first_sent_at = outstanding.sent_at;
+                } else {
+                    retransmission_acked = true;
+                }
                 self.bytes_in_flight -= seg.length_in_sequence_space();
                 self.segments_outstanding.pop_front();
                 self.timer = 0.into();
-                self.retx_timeout = self.initial_retx_timeout;
+                self.retx_timeout = self.rto;
                 self.consq_retxs = 0;
             } else {
                 break;
             }
         }
 
-        if let Some(seg) = self.segments_outstanding.front() {
-            self.receiver_free_space = ((abs_ackno + window_size as u64)
-                - WrappingU32::unwrap(&seg.header().seq_no, &self.isn, self.next_seqno)
-                - self.bytes_in_flight as u64) as _
+        // and an ACK that covers a retransmission could be for either copy
+        if retransmission_acked {
+            rtt = None;
         }
+        if let Some(sample) = rtt {
+            self.update_rto(sample);
+            self.retx_timeout = self.rto;
+        }
+        if let Some(recover) = self.recover
+            && acked_bytes > 0
+        {
+            if abs_ackno >= recover {
+                self.recover = None;
+            } else {
+                // RFC 6582: a partial ACK points straight at the next hole
+                self.retransmit_first();
+            }
+        }
+
         if self.bytes_in_flight == 0 {
             self.timer_running = false;
         }
+        if notify {
+            self.congestion.on_ack(&AckEvent {
+                ackno: abs_ackno,
+                next_seqno: self.next_seqno,
+                acked_bytes,
+                bytes_in_flight: self.bytes_in_flight,
+                rtt,
+                rate,
+                now: self.now,
+            });
+        }
         self.fill_window();
     }
 
@@ -191,62 +380,49 @@ impl TCPSender {
     }
 
     pub fn fill_window(&mut self) {
-        match (
-            self.state(),
-            self.segments_outstanding.front(),
-            self.stream_in(),
-        ) {
-            (Ok(SenderState::Closed), _, _) => {
+        match self.state() {
+            Ok(SenderState::Closed) => {
                 self.set_state(Ok(SenderState::SynSent));
                 let mut seg = TCPSegment::default();
                 seg.header_mut().syn = true;
-                self.segments_out_mut().push_back(seg);
-            }
-            (_, Some(seg), _) if seg.header().syn => {
-                self.set_state(Ok(SenderState::SynSent));
+                self.send_segment(seg);
                 return;
             }
-            (_, _, s) if s.buffer_empty() && !s.eof() => return,
-            (Ok(SenderState::FinSent), _, _) | (Ok(SenderState::FinAcked), _, _) => return,
+            Ok(SenderState::SynSent) if self.recv_ackno == 0 => return,
+            Err(_) => return,
             _ => {}
         }
 
-        match (self.receiver_window_size, self.receiver_free_space) {
-            (i, _) if i > 0 => {
-                while self.receiver_free_space > 0 {
-                    let mut seg = TCPSegment::default();
-                    let payload_len = self
-                        .stream_in
-                        .buffer_size()
-                        .min(self.receiver_free_space as _)
-                        .min(TCPConfig::MAX_PAYLOAD_SIZE);
-                    *seg.payload_mut() = Buffer::from(self.stream_in_mut().read(payload_len));
-                    if self.stream_in.eof() && self.receiver_free_space as usize > payload_len {
-                        seg.header_mut().fin = true;
-                        self.set_state(Ok(SenderState::FinSent));
-                    }
-                    self.send_segment(seg);
-                    if self.stream_in().buffer_empty() {
-                        break;
-                    }
-                }
+        // a zero window is probed with one byte at a time
+        let rwnd = (self.receiver_window_size as u64).max(1);
+        let cwnd = self.congestion.cwnd() as u64;
+        let window = rwnd.min(cwnd);
+        let paced = self.congestion.pacing_rate().is_some();
+        while !self.fin_sent() && self.next_seqno < self.recv_ackno + window {
+            if paced && self.pacing_credit <= 0.0 {
+                break;
             }
-            (_, 0) => {
-                let mut seg = TCPSegment::default();
-                match (self.stream_in.eof(), self.stream_in.buffer_empty()) {
-                    (true, _) => {
-                        seg.header_mut().fin = true;
-                        self.set_state(Ok(SenderState::FinSent));
-                        self.send_segment(seg);
-                    }
-                    (_, false) => {
-                        *seg.payload_mut() = Buffer::from(self.stream_in.read(1));
-                        self.send_segment(seg);
-                    }
-                    _ => {}
-                }
+            let remaining = (self.recv_ackno + window - self.next_seqno) as usize;
+            let sendable = self
+                .stream_in
+                .buffer_size()
+                .min(TCPConfig::MAX_PAYLOAD_SIZE);
+            // rather than a sliver the congestion window has room for, wait for the ACKs
+            // that let a full segment out; each segment in flight is worth a duplicate ACK
+            if cwnd < rwnd && remaining < sendable && self.bytes_in_flight > 0 {
+                break;
             }
-            _ => {}
+            let mut seg = TCPSegment::default();
+            let payload_len = sendable.min(remaining);
+            *seg.payload_mut() = Buffer::from(self.stream_in_mut().read(payload_len));
+            if self.stream_in.eof() && remaining > payload_len {
+                seg.header_mut().fin = true;
+                self.set_state(Ok(SenderState::FinSent));
+            }
+            if seg.length_in_sequence_space() == 0 {
+                break;
+            }
+            self.send_segment(seg);
         }
     }
 
@@ -254,6 +430,31 @@ impl TCPSender {
         self.bytes_in_flight
     }
 
+    #[inline(always)]
+    pub fn cwnd(&self) -> usize {
+        self.congestion.cwnd()
+    }
+
+    pub fn congestion_control(&self) -> &dyn CongestionControl {
+        self.congestion.as_ref()
+    }
+
+    #[inline(always)]
+    pub fn rtt_estimate(&self) -> Option<RTTEstimate> {
+        self.rtt
+    }
+
+    // the current timeout, backoff included
+    #[inline(always)]
+    pub fn retx_timeout(&self) -> Milliseconds {
+        self.retx_timeout
+    }
+
+    #[inline(always)]
+    pub fn fast_retransmits(&self) -> usize {
+        self.fast_retransmits
+    }
+
     pub fn consq_retxs(&self) -> usize {
         self.consq_retxs
     }
@@ -274,3 +475,303 @@ impl TCPSender {
         WrappingU32::wrap(self.next_seqno, &self.isn)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn closed() -> TCPSender {
+        TCPSender::with_config(&TCPConfig {
+            fixed_isn: Some(WrappingU32::new(0)),
+            ..Default::default()
+        })
+    }
+
+    fn drain(s: &mut TCPSender) -> Vec<TCPSegment> {
+        s.segments_out_mut().drain(..).collect()
+    }
+
+    fn established() -> TCPSender {
+        let mut s = closed();
+        s.fill_window();
+        drain(&mut s);
+        s.ack_received(&1.into(), 1000, false);
+        s
+    }
+
+    #[test]
+    fn test_syn_is_retransmitted() {
+        let cfg = TCPConfig::default();
+        let mut s = closed();
+        s.fill_window();
+        let segs = drain(&mut s);
+        assert_eq!(segs.len(), 1);
+        assert!(segs[0].header().syn);
+        assert_eq!(s.bytes_in_flight(), 1);
+
+        // no data goes out before the SYN is acked
+        s.stream_in_mut().write(b"abc");
+        s.fill_window();
+        assert!(drain(&mut s).is_empty());
+
+        s.tick((cfg.timeout_default as u64).into());
+        let segs = drain(&mut s);
+        assert_eq!(segs.len(), 1);
+        assert!(segs[0].header().syn);
+        // the timer restarts after a retransmission
+        s.tick(1.into());
+        assert!(drain(&mut s).is_empty());
+    }
+
+    #[test]
+    fn test_fill_window() {
+        let mut s = established();
+        s.stream_in_mut().write(&[0; 3000]);
+        s.fill_window();
+        let segs = drain(&mut s);
+        assert_eq!(
+            segs.iter().map(|seg| seg.payload().len()).sum::<usize>(),
+            1000
+        );
+        assert_eq!(s.bytes_in_flight(), 1000);
+
+        // a partial ack opens only as much room as it frees
+        s.ack_received(&501.into(), 1000, false);
+        let segs = drain(&mut s);
+        assert_eq!(
+            segs.iter().map(|seg| seg.payload().len()).sum::<usize>(),
+            500
+        );
+        assert_eq!(segs[0].header().seq_no, 1001.into());
+    }
+
+    #[test]
+    fn test_zero_window_probe() {
+        let mut s = established();
+        s.ack_received(&1.into(), 0, false);
+        s.stream_in_mut().write(b"abc");
+        s.fill_window();
+        let segs = drain(&mut s);
+        assert_eq!(segs.len(), 1);
+        assert_eq!(segs[0].payload().as_ref(), b"a");
+        s.fill_window();
+        assert!(drain(&mut s).is_empty());
+    }
+
+    #[test]
+    fn test_fin() {
+        let mut s = established();
+        s.stream_in_mut().write(b"abc");
+        s.stream_in_mut().end_input();
+        s.fill_window();
+        let segs = drain(&mut s);
+        assert_eq!(segs.len(), 1);
+        assert!(segs[0].header().fin);
+        assert_eq!(s.bytes_in_flight(), 4);
+        assert!(matches!(s.renew_state(), Ok(SenderState::FinSent)));
+
+        // the FIN is sent only once
+        s.fill_window();
+        assert!(drain(&mut s).is_empty());
+        s.ack_received(&5.into(), 1000, false);
+        assert!(matches!(s.renew_state(), Ok(SenderState::FinAcked)));
+    }
+
+    #[test]
+    fn test_fin_waits_for_window() {
+        let mut s = established();
+        s.ack_received(&1.into(), 3, false);
+        s.stream_in_mut().write(b"abc");
+        s.stream_in_mut().end_input();
+        s.fill_window();
+        let segs = drain(&mut s);
+        assert_eq!(segs.len(), 1);
+        assert!(!segs[0].header().fin);
+
+        s.ack_received(&4.into(), 3, false);
+        let segs = drain(&mut s);
+        assert_eq!(segs.len(), 1);
+        assert!(segs[0].header().fin);
+        assert_eq!(segs[0].header().seq_no, 4.into());
+    }
+
+    fn sender(min_rto: u16, max_rto: u16) -> TCPSender {
+        let cfg = TCPConfig {
+            fixed_isn: Some(WrappingU32::new(0)),
+            min_rto,
+            max_rto,
+            ..Default::default()
+        };
+        let mut sender = TCPSender::with_config(&cfg);
+        sender.fill_window();
+        sender
+    }
+
+    fn send(sender: &mut TCPSender, data: &[u8]) {
+        sender.stream_in_mut().write(data);
+        sender.fill_window();
+    }
+
+    fn ack(sender: &mut TCPSender, ackno: u32) {
+        sender.ack_received(&WrappingU32::new(ackno), 60000, false);
+    }
+
+    #[test]
+    fn test_rtt_estimate() {
+        let mut sender = sender(10, TCPConfig::MAX_RTO);
+        assert_eq!(sender.rtt_estimate(), None);
+        assert_eq!(
+            sender.retx_timeout(),
+            (TCPConfig::TIMEOUT_DFLT as u64).into()
+        );
+
+        sender.tick(50.into());
+        ack(&mut sender, 1);
+        let rtt = sender.rtt_estimate().unwrap();
+        assert_eq!((rtt.srtt, rtt.rttvar, rtt.latest), (50.0, 25.0, 50.into()));
+        assert_eq!(sender.retx_timeout(), 150.into());
+
+        send(&mut sender, b"hello");
+        sender.tick(30.into());
+        ack(&mut sender, 6);
+        let rtt = sender.rtt_estimate().unwrap();
+        assert_eq!((rtt.srtt, rtt.rttvar, rtt.latest), (47.5, 23.75, 30.into()));
+        assert_eq!(sender.retx_timeout(), 143.into());
+    }
+
+    #[test]
+    fn test_rto_bounds() {
+        let mut sender = sender(200, 300);
+        assert_eq!(sender.retx_timeout(), 300.into());
+        sender.tick(10.into());
+        ack(&mut sender, 1);
+        assert_eq!(sender.retx_timeout(), 200.into());
+
+        // backoff stops at the maximum
+        send(&mut sender, b"hello");
+        sender.segments_out_mut().clear();
+        for (timeout, backed_off) in [(200, 300), (300, 300)] {
+            sender.tick((timeout - 1).into());
+            assert!(sender.segments_out().is_empty());
+            sender.tick(1.into());
+            assert_eq!(sender.segments_out_mut().drain(..).count(), 1);
+            assert_eq!(sender.retx_timeout(), backed_off.into());
+        }
+    }
+
+    #[test]
+    fn test_inverted_rto_bounds() {
+        let mut sender = sender(300, 200);
+        assert_eq!(sender.retx_timeout(), 200.into());
+        sender.tick(10.into());
+        ack(&mut sender, 1);
+        assert_eq!(sender.retx_timeout(), 200.into());
+    }
+
+    #[test]
+    fn test_karn() {
+        let mut sender = sender(10, TCPConfig::MAX_RTO);
+        sender.tick(20.into());
+        ack(&mut sender, 1);
+        send(&mut sender, b"hello");
+
+        // the ACK might be for either copy, so it's no sample
+        sender.tick(60.into());
+        assert_eq!(sender.consq_retxs(), 1);
+        sender.tick(5.into());
+        ack(&mut sender, 6);
+        assert_eq!(sender.rtt_estimate().unwrap().latest, 20.into());
+
+        send(&mut sender, b"world");
+        sender.tick(5.into());
+        ack(&mut sender, 11);
+        assert_eq!(sender.rtt_estimate().unwrap().latest, 5.into());
+    }
+
+    #[test]
+    fn test_fast_retransmit() {
+        const MSS: u32 = TCPConfig::MAX_PAYLOAD_SIZE as u32;
+        let mut sender = sender(10, TCPConfig::MAX_RTO);
+        sender.tick(20.into());
+        ack(&mut sender, 1);
+        sender.segments_out_mut().clear();
+        send(&mut sender, &[0; 3 * MSS as usize]);
+        assert_eq!(sender.segments_out_mut().drain(..).count(), 3);
+        let retx_timeout = sender.retx_timeout();
+
+        // an ACK that carries data or moves the window isn't a duplicate
+        for _ in 0..3 {
+            sender.ack_received(&WrappingU32::new(1), 60000, true);
+        }
+        sender.ack_received(&WrappingU32::new(1), 50000, false);
+        assert!(sender.segments_out().is_empty());
+
+        let retransmitted = |sender: &mut TCPSender| -> Vec<u32> {
+            let segs = sender.segments_out_mut().drain(..);
+            segs.map(|seg| seg.header().seq_no.raw_val()).collect()
+        };
+        sender.ack_received(&WrappingU32::new(1), 50000, false);
+        sender.ack_received(&WrappingU32::new(1), 50000, false);
+        assert!(sender.segments_out().is_empty());
+        sender.ack_received(&WrappingU32::new(1), 50000, false);
+        assert_eq!(retransmitted(&mut sender), vec![1]);
+        assert_eq!(sender.fast_retransmits(), 1);
+        assert_eq!(sender.consq_retxs(), 0);
+        assert_eq!(sender.retx_timeout(), retx_timeout);
+
+        // more duplicates from the same window don't trigger another one
+        for _ in 0..3 {
+            sender.ack_received(&WrappingU32::new(1), 50000, false);
+        }
+        assert!(retransmitted(&mut sender).is_empty());
+
+        // a partial ACK retransmits the next hole straight away
+        sender.ack_received(&WrappingU32::new(1 + MSS), 50000, false);
+        assert_eq!(retransmitted(&mut sender), vec![1 + MSS]);
+        sender.ack_received(&WrappingU32::new(1 + 3 * MSS), 50000, false);
+        assert!(retransmitted(&mut sender).is_empty());
+        assert_eq!(sender.fast_retransmits(), 1);
+    }
+
+    #[test]
+    fn test_zero_window_timeout_is_no_loss() {
+        const MSS: u32 = TCPConfig::MAX_PAYLOAD_SIZE as u32;
+        let mut sender = sender(10, TCPConfig::MAX_RTO);
+        ack(&mut sender, 1);
+        send(&mut sender, &[0; 3 * MSS as usize]);
+        sender.ack_received(&WrappingU32::new(1 + MSS), 0, false);
+        sender.segments_out_mut().clear();
+
+        sender.tick(sender.retx_timeout());
+        assert_eq!(sender.segments_out_mut().drain(..).count(), 1);
+        assert_eq!(sender.consq_retxs(), 0);
+
+        // so an ACK short of everything sent isn't taken for a partial ACK in recovery
+        sender.ack_received(&WrappingU32::new(1 + 2 * MSS), 0, false);
+        assert!(sender.segments_out().is_empty());
+    }
+
+    #[test]
+    fn test_new_reno_fast_recovery() {
+        const MSS: usize = TCPConfig::MAX_PAYLOAD_SIZE;
+        let mut sender = sender(10, TCPConfig::MAX_RTO);
+        ack(&mut sender, 1);
+        send(&mut sender, &[0; 3 * MSS]);
+        assert_eq!(sender.bytes_in_flight(), 3 * MSS);
+
+        // the third duplicate halves the window and inflates it by the three that left
+        for _ in 0..3 {
+            sender.ack_received(&WrappingU32::new(1), 60000, false);
+        }
+        assert_eq!(sender.fast_retransmits(), 1);
+        assert_eq!(sender.cwnd(), 2 * MSS + 3 * MSS);
+
+        // each further duplicate inflates it by another segment
+        sender.ack_received(&WrappingU32::new(1), 60000, false);
+        assert_eq!(sender.cwnd(), 6 * MSS);
+
+        // the full ACK deflates it back to ssthresh
+        ack(&mut sender, 1 + 3 * MSS as u32);
+        assert_eq!(sender.cwnd(), 2 * MSS);
+    }
+}
@@ -12,7 +12,7 @@ impl Sub for WrappingU32 {
 
     #[inline(always)]
     fn sub(self, other: Self) -> Self::Output {
-        (self.raw_val - other.raw_val) as _
+        self.raw_val.wrapping_sub(other.raw_val) as _
     }
 }
 
@@ -30,10 +30,10 @@ impl From<u32> for WrappingU32 {
     }
 }
 
-impl Into<u32> for WrappingU32 {
+impl From<WrappingU32> for u32 {
     #[inline(always)]
-    fn into(self) -> u32 {
-        self.raw_val
+    fn from(val: WrappingU32) -> Self {
+        val.raw_val
     }
 }
 
@@ -64,7 +64,7 @@ impl WrappingU32 {
 
     #[inline(always)]
     pub fn wrap(n: u64, isn: &Self) -> Self {
-        let raw_val = n as u32 + isn.raw_val;
+        let raw_val = (n as u32).wrapping_add(isn.raw_val);
         Self { raw_val }
     }
 
@@ -74,7 +74,10 @@ impl WrappingU32 {
 
         let candidate_current = base + offset;
         let candidate_next = candidate_current + (1 << 32);
-        let candidate_prev = candidate_current.wrapping_sub(1 << 32);
+        let candidate_prev = match base {
+            0 => candidate_current,
+            _ => candidate_current - (1 << 32),
+        };
 
         let dist_current = Self::signed_distance(candidate_current, check_point);
         let dist_next = Self::signed_distance(candidate_next, check_point);
@@ -93,3 +96,43 @@ impl WrappingU32 {
         a as i64 - b as i64
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_wrap() {
+        assert_eq!(WrappingU32::wrap(3 << 32, &0.into()), 0.into());
+        assert_eq!(WrappingU32::wrap((3 << 32) + 17, &15.into()), 32.into());
+        // wraps past u32::MAX instead of overflowing
+        assert_eq!(WrappingU32::wrap(17, &u32::MAX.into()), 16.into());
+        assert_eq!(WrappingU32::wrap((7 << 32) - 2, &15.into()), 13.into());
+    }
+
+    #[test]
+    fn test_unwrap() {
+        let isn = WrappingU32::new(0);
+        assert_eq!(WrappingU32::unwrap(&1.into(), &isn, 0), 1);
+        assert_eq!(
+            WrappingU32::unwrap(&1.into(), &isn, u32::MAX as u64),
+            (1 << 32) + 1
+        );
+        assert_eq!(
+            WrappingU32::unwrap(&(u32::MAX - 1).into(), &isn, 3 << 32),
+            (3 << 32) - 2
+        );
+        // a seqno just below the ISN near the start of the stream must not go negative
+        assert_eq!(
+            WrappingU32::unwrap(&u32::MAX.into(), &isn, 0),
+            u32::MAX as u64
+        );
+        assert_eq!(WrappingU32::unwrap(&5.into(), &10.into(), 0), (1 << 32) - 5);
+    }
+
+    #[test]
+    fn test_sub() {
+        assert_eq!(WrappingU32::new(5) - WrappingU32::new(u32::MAX), 6);
+        assert_eq!(WrappingU32::new(u32::MAX) - WrappingU32::new(5), -6);
+    }
+}
@@ -10,6 +10,9 @@ pub use tcp_segment::*;
 pub mod tcp_header;
 pub use tcp_header::*;
 
+pub mod tcp_option;
+pub use tcp_option::*;
+
 pub mod tcp_state;
 pub use tcp_state::*;
 
@@ -19,6 +22,9 @@ pub use ethernet_frame::*;
 pub mod ethernet_header;
 pub use ethernet_header::*;
 
+pub mod arp_message;
+pub use arp_message::*;
+
 pub mod lossy_fd_adapter;
 pub use lossy_fd_adapter::*;
 
@@ -0,0 +1,165 @@
+use crate::{Buffer, EthernetAddress, EthernetHeader, NetParser, NetUnparser, ParseError};
+
+use std::{
+    fmt::{self, Display, Formatter},
+    net::Ipv4Addr,
+};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ARPMessage {
+    pub hardware_type: u16,
+    pub protocol_type: u16,
+    pub hardware_address_size: u8,
+    pub protocol_address_size: u8,
+    pub opcode: u16,
+    pub sender_ethernet_address: EthernetAddress,
+    pub sender_ip_address: u32,
+    pub target_ethernet_address: EthernetAddress,
+    pub target_ip_address: u32,
+}
+
+impl Default for ARPMessage {
+    fn default() -> Self {
+        Self {
+            hardware_type: Self::TYPE_ETHERNET,
+            protocol_type: EthernetHeader::TYPE_IPV4,
+            hardware_address_size: 6,
+            protocol_address_size: 4,
+            opcode: 0,
+            sender_ethernet_address: EthernetAddress::default(),
+            sender_ip_address: 0,
+            target_ethernet_address: EthernetAddress::default(),
+            target_ip_address: 0,
+        }
+    }
+}
+
+impl ARPMessage {
+    pub const LENGTH: usize = 28;
+    pub const TYPE_ETHERNET: u16 = 1;
+    pub const OPCODE_REQUEST: u16 = 1;
+    pub const OPCODE_REPLY: u16 = 2;
+
+    pub fn supported(&self) -> bool {
+        self.hardware_type == Self::TYPE_ETHERNET
+            && self.protocol_type == EthernetHeader::TYPE_IPV4
+            && self.hardware_address_size == 6
+            && self.protocol_address_size == 4
+            && (self.opcode == Self::OPCODE_REQUEST || self.opcode == Self::OPCODE_REPLY)
+    }
+
+    pub fn parse(&mut self, buf: Buffer) -> Result<(), ParseError> {
+        if buf.len() < Self::LENGTH {
+            return Err(ParseError::PacketTooShort);
+        }
+
+        let mut p = NetParser::new(buf);
+        self.hardware_type = p.parse_u16();
+        self.protocol_type = p.parse_u16();
+        self.hardware_address_size = p.parse_u8();
+        self.protocol_address_size = p.parse_u8();
+        self.opcode = p.parse_u16();
+
+        if !self.supported() {
+            return Err(ParseError::Unsupported);
+        }
+
+        self.sender_ethernet_address
+            .iter_mut()
+            .for_each(|byte| *byte = p.parse_u8());
+        self.sender_ip_address = p.parse_u32();
+        self.target_ethernet_address
+            .iter_mut()
+            .for_each(|byte| *byte = p.parse_u8());
+        self.target_ip_address = p.parse_u32();
+        p.get_result()
+    }
+
+    pub fn serialize(&self) -> Result<Vec<u8>, ParseError> {
+        if !self.supported() {
+            return Err(ParseError::Unsupported);
+        }
+
+        let mut ser = Vec::with_capacity(Self::LENGTH);
+        NetUnparser::u16(&mut ser, self.hardware_type);
+        NetUnparser::u16(&mut ser, self.protocol_type);
+        NetUnparser::u8(&mut ser, self.hardware_address_size);
+        NetUnparser::u8(&mut ser, self.protocol_address_size);
+        NetUnparser::u16(&mut ser, self.opcode);
+        self.sender_ethernet_address
+            .iter()
+            .for_each(|&b| NetUnparser::u8(&mut ser, b));
+        NetUnparser::u32(&mut ser, self.sender_ip_address);
+        self.target_ethernet_address
+            .iter()
+            .for_each(|&b| NetUnparser::u8(&mut ser, b));
+        NetUnparser::u32(&mut ser, self.target_ip_address);
+        Ok(ser)
+    }
+}
+
+impl Display for ARPMessage {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let opcode = match self.opcode {
+            Self::OPCODE_REQUEST => "REQUEST".to_string(),
+            Self::OPCODE_REPLY => "REPLY".to_string(),
+            _ => format!("(unknown type {})", self.opcode),
+        };
+        write!(
+            f,
+            "opcode={}, sender={}/{}, target={}/{}",
+            opcode,
+            self.sender_ethernet_address,
+            Ipv4Addr::from(self.sender_ip_address),
+            self.target_ethernet_address,
+            Ipv4Addr::from(self.target_ip_address)
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request() -> ARPMessage {
+        ARPMessage {
+            opcode: ARPMessage::OPCODE_REQUEST,
+            sender_ethernet_address: EthernetAddress([0x02, 0, 0, 0, 0, 0x01]),
+            sender_ip_address: 0x0a00_0001,
+            target_ip_address: 0x0a00_0002,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let msg = request();
+        let ser = msg.serialize().unwrap();
+        assert_eq!(ser.len(), ARPMessage::LENGTH);
+
+        let mut parsed = ARPMessage::default();
+        parsed.parse(ser.into()).unwrap();
+        assert_eq!(parsed, msg);
+        assert_eq!(
+            parsed.to_string(),
+            "opcode=REQUEST, sender=02:00:00:00:00:01/10.0.0.1, target=00:00:00:00:00:00/10.0.0.2"
+        );
+    }
+
+    #[test]
+    fn test_unsupported() {
+        let mut ser = request().serialize().unwrap();
+        ser[1] = 6; // hardware type other than Ethernet
+        let mut parsed = ARPMessage::default();
+        assert!(matches!(
+            parsed.parse(ser.into()),
+            Err(ParseError::Unsupported)
+        ));
+
+        let mut parsed = ARPMessage::default();
+        assert!(matches!(
+            parsed.parse(vec![0; 10].into()),
+            Err(ParseError::PacketTooShort)
+        ));
+    }
+}
@@ -1,5 +1,6 @@
 use crate::{Buffer, BufferList, EthernetHeader, NetParser, ParseError};
 
+#[derive(Debug, Default, Clone)]
 pub struct EthernetFrame {
     header: EthernetHeader,
     payload: BufferList,
@@ -22,14 +23,14 @@ impl EthernetFrame {
     pub fn header(&self) -> &EthernetHeader {
         &self.header
     }
-    pub fn header_mut(&mut self) -> &EthernetHeader {
+    pub fn header_mut(&mut self) -> &mut EthernetHeader {
         &mut self.header
     }
 
     pub fn payload(&self) -> &BufferList {
         &self.payload
     }
-    pub fn payload_mut(&mut self) -> &BufferList {
+    pub fn payload_mut(&mut self) -> &mut BufferList {
         &mut self.payload
     }
 }
@@ -1,13 +1,17 @@
 use crate::{NetParser, NetUnparser, ParseError};
 
+use anyhow::{Error, Result, anyhow};
 use itertools::Itertools;
+use rand::random;
 
 use std::{
     fmt::{self, Display, Formatter},
     slice::{Iter, IterMut},
+    str::FromStr,
 };
 
-pub struct EthernetAddress([u8; 6]);
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct EthernetAddress(pub [u8; 6]);
 pub const ETHERNETBROADCAST: EthernetAddress = EthernetAddress([0xff; 6]);
 
 impl EthernetAddress {
@@ -18,8 +22,17 @@ impl EthernetAddress {
     pub fn iter_mut(&mut self) -> IterMut<'_, u8> {
         self.0.iter_mut()
     }
+
+    pub fn random_private() -> Self {
+        let mut addr: [u8; 6] = random();
+        // locally administered, unicast
+        addr[0] |= 0x02;
+        addr[0] &= 0xfe;
+        Self(addr)
+    }
 }
 
+#[derive(Debug, Default, Clone)]
 pub struct EthernetHeader {
     pub dst: EthernetAddress,
     pub src: EthernetAddress,
@@ -27,9 +40,9 @@ pub struct EthernetHeader {
 }
 
 impl EthernetHeader {
-    const LENGTH: usize = 14;
-    const TYPE_IPV4: u16 = 0x800;
-    const TYPE_ARP: u16 = 0x806;
+    pub const LENGTH: usize = 14;
+    pub const TYPE_IPV4: u16 = 0x800;
+    pub const TYPE_ARP: u16 = 0x806;
 
     pub fn parse(&mut self, p: &mut NetParser) -> Result<(), ParseError> {
         if p.buffer().len() < Self::LENGTH {
@@ -61,6 +74,26 @@ impl Display for EthernetAddress {
     }
 }
 
+impl FromStr for EthernetAddress {
+    type Err = Error;
+
+    // parses the "xx:xx:xx:xx:xx:xx" form that Display prints
+    fn from_str(s: &str) -> Result<Self> {
+        let bytes: Vec<u8> = s
+            .split(':')
+            .map(|b| match b.len() {
+                1 | 2 => u8::from_str_radix(b, 16).map_err(Error::from),
+                _ => Err(anyhow!("bad octet {:?}", b)),
+            })
+            .collect::<Result<_>>()
+            .map_err(|err| anyhow!("invalid Ethernet address {}: {}", s, err))?;
+        let addr = bytes
+            .try_into()
+            .map_err(|_| anyhow!("invalid Ethernet address {}: expected 6 octets", s))?;
+        Ok(Self(addr))
+    }
+}
+
 impl Display for EthernetHeader {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(
@@ -76,3 +109,25 @@ impl Display for EthernetHeader {
         )
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ethernet_address_from_str() {
+        let addr: EthernetAddress = "02:0a:ff:3:00:9c".parse().unwrap();
+        assert_eq!(addr, EthernetAddress([0x02, 0x0a, 0xff, 0x03, 0x00, 0x9c]));
+        assert_eq!(addr.to_string().parse::<EthernetAddress>().unwrap(), addr);
+
+        for bad in [
+            "",
+            "02:0a:ff:03:00",
+            "02:0a:ff:03:00:9c:01",
+            "02:0a:ff:03:00:xx",
+            "020:a:ff:03:00:9c",
+        ] {
+            assert!(bad.parse::<EthernetAddress>().is_err(), "{:?}", bad);
+        }
+    }
+}
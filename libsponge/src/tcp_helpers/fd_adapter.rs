@@ -1,8 +1,23 @@
-use crate::{FDAdapterConfig, Milliseconds};
+use crate::{
+    FDAdapterConfig, FDAdaptor, Milliseconds, NakedFileDescriptor, NoneAdapter, TCPSegment,
+    UDPSocket,
+};
 
-use std::marker::PhantomData;
+use std::{
+    marker::PhantomData,
+    ops::{Deref, DerefMut},
+};
+
+pub trait DatagramAdapter {
+    fn fd(&self) -> NakedFileDescriptor;
+    fn read(&mut self) -> Option<TCPSegment>;
+    fn write(&mut self, seg: &mut TCPSegment);
+    fn set_listening(&mut self, l: bool);
+    fn cfg(&self) -> &FDAdapterConfig;
+    fn cfg_mut(&mut self) -> &mut FDAdapterConfig;
+    fn tick(&mut self, elapsed: Milliseconds);
+}
 
-#[derive(Default)]
 pub struct FDAdapterBase<T, L> {
     cfg: FDAdapterConfig,
     listen: bool,
@@ -11,6 +26,17 @@ pub struct FDAdapterBase<T, L> {
     _lossy: PhantomData<L>,
 }
 
+impl<T, L> Default for FDAdapterBase<T, L> {
+    fn default() -> Self {
+        Self {
+            cfg: FDAdapterConfig::default(),
+            listen: false,
+            _type: PhantomData,
+            _lossy: PhantomData,
+        }
+    }
+}
+
 impl<T, L> FDAdapterBase<T, L> {
     pub fn set_listening(&mut self, l: bool) {
         self.listen = l;
@@ -28,5 +54,108 @@ impl<T, L> FDAdapterBase<T, L> {
         &mut self.cfg
     }
 
-    fn tick(&mut self, elapsed: Milliseconds) {}
+    pub fn tick(&mut self, _elapsed: Milliseconds) {}
+}
+
+pub struct TCPOverUDP;
+
+pub struct TCPOverUDPAdapter {
+    adapter: FDAdaptor<TCPOverUDP>,
+    socket: UDPSocket<NoneAdapter>,
+}
+
+impl Deref for TCPOverUDPAdapter {
+    type Target = FDAdaptor<TCPOverUDP>;
+
+    fn deref(&self) -> &FDAdaptor<TCPOverUDP> {
+        &self.adapter
+    }
+}
+
+impl DerefMut for TCPOverUDPAdapter {
+    fn deref_mut(&mut self) -> &mut FDAdaptor<TCPOverUDP> {
+        &mut self.adapter
+    }
+}
+
+impl TCPOverUDPAdapter {
+    pub fn new(socket: UDPSocket<NoneAdapter>) -> Self {
+        Self {
+            adapter: FDAdaptor::default(),
+            socket,
+        }
+    }
+
+    #[inline(always)]
+    pub fn socket(&self) -> &UDPSocket<NoneAdapter> {
+        &self.socket
+    }
+}
+
+impl DatagramAdapter for TCPOverUDPAdapter {
+    fn fd(&self) -> NakedFileDescriptor {
+        (&self.socket).into()
+    }
+
+    fn read(&mut self) -> Option<TCPSegment> {
+        let datagram = match self.socket.recv(None) {
+            Ok(datagram) => datagram,
+            Err(err) => {
+                eprintln!("TCPOverUDPAdapter: {}", err);
+                return None;
+            }
+        };
+
+        // is it for us?
+        if !self.adapter.listen() && *datagram.src_addr() != self.cfg().destination {
+            return None;
+        }
+
+        let src_addr = datagram.src_addr().clone();
+        let mut seg = TCPSegment::default();
+        seg.parse(datagram.payload.into(), 0).ok()?;
+
+        // the first SYN tells a listening adapter whom to reply to
+        if self.adapter.listen() {
+            if !seg.header().syn || seg.header().rst {
+                return None;
+            }
+            self.cfg_mut().destination = src_addr;
+            self.adapter.set_listening(false);
+        }
+        Some(seg)
+    }
+
+    fn write(&mut self, seg: &mut TCPSegment) {
+        let cfg = self.adapter.cfg();
+        let (Ok(src_port), Ok(dst_port)) = (cfg.source.port(), cfg.destination.port()) else {
+            return eprintln!("TCPOverUDPAdapter: source or destination address is unresolved");
+        };
+        seg.header_mut().src_port = src_port;
+        seg.header_mut().dst_port = dst_port;
+
+        let result = seg
+            .serialize(0)
+            .map_err(anyhow::Error::from)
+            .and_then(|payload| self.socket.send_to(&cfg.destination, &(&payload).into()));
+        if let Err(err) = result {
+            eprintln!("TCPOverUDPAdapter: {}", err);
+        }
+    }
+
+    fn set_listening(&mut self, l: bool) {
+        self.adapter.set_listening(l)
+    }
+
+    fn cfg(&self) -> &FDAdapterConfig {
+        self.adapter.cfg()
+    }
+
+    fn cfg_mut(&mut self) -> &mut FDAdapterConfig {
+        self.adapter.cfg_mut()
+    }
+
+    fn tick(&mut self, elapsed: Milliseconds) {
+        self.adapter.tick(elapsed)
+    }
 }
@@ -1,6 +1,6 @@
-use crate::{Buffer, BufferList, IPv4Header, InternetChecksum, NetParser, ParseError};
+use crate::{Buffer, BufferList, IPv4Header, NetParser, ParseError};
 
-#[derive(Default)]
+#[derive(Default, Clone)]
 pub struct IPv4Datagram {
     header: IPv4Header,
     payload: BufferList,
@@ -24,12 +24,7 @@ impl IPv4Datagram {
         }
 
         let mut header_out = self.header;
-        header_out.cksum = 0;
-        let header_zero_checksum = header_out.try_serialize()?;
-
-        let mut checksum = InternetChecksum::default();
-        checksum.add(header_zero_checksum.as_slice());
-        header_out.cksum = checksum.value();
+        header_out.recompute_cksum()?;
 
         let mut ret = BufferList::default();
         ret.append(header_out.try_serialize()?.into());
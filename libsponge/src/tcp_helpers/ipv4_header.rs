@@ -1,4 +1,7 @@
-use std::fmt::{Debug, Display};
+use std::{
+    fmt::{Debug, Display},
+    net::Ipv4Addr,
+};
 
 use crate::{InternetChecksum, NetParser, NetUnparser, ParseError};
 
@@ -12,7 +15,7 @@ pub struct IPv4Header {
     df: bool,
     mf: bool,
     offset: u16,
-    ttl: u8,
+    pub ttl: u8,
     pub proto: u8,
     pub cksum: u16,
     pub src: u32,
@@ -46,52 +49,46 @@ impl Default for IPv4Header {
 }
 
 impl IPv4Header {
-    #[allow(unused_variables)]
-    pub fn try_parse(&self, p: &mut NetParser) -> Result<(), ParseError> {
-        let original_serialized_version = p.buffer();
-        let mut v = original_serialized_version.as_ref().to_vec();
-        let len = original_serialized_version.len();
-        if len < IPv4Header::LENGTH {
+    pub fn try_parse(&mut self, p: &mut NetParser) -> Result<(), ParseError> {
+        let original_serialized_version = p.buffer().as_ref().to_vec();
+        let data_len = original_serialized_version.len();
+        if data_len < IPv4Header::LENGTH {
             return Err(ParseError::PacketTooShort);
         }
 
         let first_byte = p.parse_u8();
-        let ver = first_byte >> 4;
-        if ver != 4 {
+        self.ver = first_byte >> 4;
+        self.hlen = first_byte & 0x0f;
+        self.tos = p.parse_u8();
+        self.len = p.parse_u16();
+        self.id = p.parse_u16();
+        let fo_val = p.parse_u16();
+        self.df = (fo_val & 0x4000) != 0;
+        self.mf = (fo_val & 0x2000) != 0;
+        self.offset = fo_val & 0x1fff;
+        self.ttl = p.parse_u8();
+        self.proto = p.parse_u8();
+        self.cksum = p.parse_u16();
+        self.src = p.parse_u32();
+        self.dst = p.parse_u32();
+
+        if self.ver != 4 {
             return Err(ParseError::WrongIPVersion);
         }
-
-        let hlen = first_byte & 0x0f;
-        if hlen < 5 {
+        if self.hlen < 5 {
             return Err(ParseError::HeaderTooShort);
         }
-        let tos = p.parse_u8();
-        let len = p.parse_u16();
-        if (len as u8) < 4 * hlen {
-            return Err(ParseError::PacketTooShort);
-        }
-        let id = p.parse_u16();
-        let fo_val = p.parse_u16();
-        let df = (fo_val & 0x4000) != 0;
-        let mf = (fo_val & 0x2000) != 0;
-        let offset = fo_val & 0x1fff;
-        let ttl = p.parse_u8();
-        let proto = p.parse_u8();
-        let cksum = p.parse_u16();
-        let src = p.parse_u32();
-        let dst = p.parse_u32();
-
-        if len != p.buffer().len() as _ {
+        if data_len != self.len as usize {
             return Err(ParseError::TruncatedPacket);
         }
 
-        p.remove_prefix(hlen as usize * 4 - Self::LENGTH);
+        p.remove_prefix(self.hlen as usize * 4 - Self::LENGTH);
         if p.is_err() {
             return p.get_result();
         }
+
         let mut checksum = InternetChecksum::default();
-        v.push(4 * hlen);
-        checksum.add(&v);
+        checksum.add(&original_serialized_version[..4 * self.hlen as usize]);
         if checksum.value() != 0 {
             return Err(ParseError::BadChecksum);
         }
@@ -102,7 +99,7 @@ impl IPv4Header {
         if self.ver != 4 {
             return Err(ParseError::WrongIPVersion);
         }
-        if self.hlen as usize * 5 < Self::LENGTH {
+        if self.hlen as usize * 4 < Self::LENGTH {
             return Err(ParseError::HeaderTooShort);
         }
 
@@ -125,14 +122,22 @@ impl IPv4Header {
         Ok(ret)
     }
 
+    pub fn recompute_cksum(&mut self) -> Result<(), ParseError> {
+        self.cksum = 0;
+        let mut checksum = InternetChecksum::default();
+        checksum.add(&self.try_serialize()?);
+        self.cksum = checksum.value();
+        Ok(())
+    }
+
     #[inline(always)]
     pub fn payload_length(&self) -> u16 {
-        self.len - self.hlen as u16
+        self.len - 4 * self.hlen as u16
     }
 
     pub fn pseudo_cksum(&self) -> u32 {
-        let mut pcksum = self.src >> 16 + self.src & 0xffff;
-        pcksum += self.dst >> 16 + self.dst & 0xffff;
+        let mut pcksum = (self.src >> 16) + (self.src & 0xffff);
+        pcksum += (self.dst >> 16) + (self.dst & 0xffff);
         pcksum += self.proto as u32;
         pcksum += self.payload_length() as u32;
         pcksum
@@ -143,17 +148,17 @@ impl Display for IPv4Header {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(
             f,
-            "IPv{}, len={}, protocol={}, {}, src={}, dst={}",
-            self.ver,
-            self.len,
-            self.proto,
-            if self.ttl >= 10 {
-                format!("")
-            } else {
-                format!("ttl={}", self.ttl)
-            },
-            self.src.to_be(),
-            self.dst.to_be()
+            "IPv{}, len={}, protocol={}, ",
+            self.ver, self.len, self.proto
+        )?;
+        if self.ttl < 10 {
+            write!(f, "ttl={}, ", self.ttl)?;
+        }
+        write!(
+            f,
+            "src={}, dst={}",
+            Ipv4Addr::from(self.src),
+            Ipv4Addr::from(self.dst)
         )
     }
 }
@@ -179,3 +184,28 @@ impl Debug for IPv4Header {
         )
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_display() {
+        let mut header = IPv4Header {
+            len: 40,
+            src: u32::from(Ipv4Addr::new(10, 0, 0, 1)),
+            dst: u32::from(Ipv4Addr::new(169, 254, 144, 9)),
+            ..Default::default()
+        };
+        assert_eq!(
+            header.to_string(),
+            "IPv4, len=40, protocol=6, src=10.0.0.1, dst=169.254.144.9"
+        );
+
+        header.ttl = 1;
+        assert_eq!(
+            header.to_string(),
+            "IPv4, len=40, protocol=6, ttl=1, src=10.0.0.1, dst=169.254.144.9"
+        );
+    }
+}
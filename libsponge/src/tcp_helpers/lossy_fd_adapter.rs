@@ -1,65 +1,168 @@
-use crate::FDAdapterBase;
+use crate::{
+    DatagramAdapter, FDAdapterBase, FDAdapterConfig, Milliseconds, NakedFileDescriptor, TCPSegment,
+};
 
-use rand::random;
+use rand::{Rng, SeedableRng, rngs::StdRng};
 
-struct Lossy;
 pub struct NoneLossy;
-pub type LossyFDAdaptor<T> = FDAdapterBase<T, Lossy>;
 pub type FDAdaptor<T> = FDAdapterBase<T, NoneLossy>;
 
-impl<T> LossyFDAdaptor<T> {
-    fn should_drop(&self, uplink: bool) -> bool {
-        unimplemented!()
+pub struct LossyFDAdaptor<A> {
+    adapter: A,
+    rng: StdRng,
+}
+
+impl<A: DatagramAdapter> LossyFDAdaptor<A> {
+    fn should_drop(&mut self, uplink: bool) -> bool {
+        let cfg = self.adapter.cfg();
+        let loss = if uplink {
+            cfg.loss_rate_up
+        } else {
+            cfg.loss_rate_dn
+        };
+        loss != 0 && self.rng.random::<u16>() < loss
+    }
+}
+
+impl<A: DatagramAdapter> LossyFDAdaptor<A> {
+    pub fn new(adapter: A) -> Self {
+        Self {
+            adapter,
+            rng: StdRng::from_os_rng(),
+        }
+    }
+
+    pub fn with_seed(adapter: A, seed: u64) -> Self {
+        Self {
+            adapter,
+            rng: StdRng::seed_from_u64(seed),
+        }
+    }
+
+    #[inline(always)]
+    pub fn adapter(&self) -> &A {
+        &self.adapter
+    }
+
+    #[inline(always)]
+    pub fn adapter_mut(&mut self) -> &mut A {
+        &mut self.adapter
     }
 }
 
-//     //! \brief Determine whether or not to drop a given read or write
-//     //! \param[in] uplink is `true` to use the uplink loss probability, else use the downlink loss probability
-//     //! \returns `true` if the segment should be dropped
-//     bool _should_drop(bool uplink) {
-//         const auto &cfg = _adapter.config();
-//         const uint16_t loss = uplink ? cfg.loss_rate_up : cfg.loss_rate_dn;
-//         return loss != 0 && uint16_t(_rand()) < loss;
-//     }
-
-//   public:
-//     //! Conversion to a FileDescriptor by returning the underlying AdapterT
-//     operator const FileDescriptor &() const { return _adapter; }
-
-//     //! Construct from a FileDescriptor appropriate to the AdapterT constructor
-//     explicit LossyFdAdapter(AdapterT &&adapter) : _adapter(std::move(adapter)) {}
-
-//     //! \brief Read from the underlying AdapterT instance, potentially dropping the read datagram
-//     //! \returns std::optional<TCPSegment> that is empty if the segment was dropped or if
-//     //!          the underlying AdapterT returned an empty value
-//     std::optional<TCPSegment> read() {
-//         auto ret = _adapter.read();
-//         if (_should_drop(false)) {
-//             return {};
-//         }
-//         return ret;
-//     }
-
-//     //! \brief Write to the underlying AdapterT instance, potentially dropping the datagram to be written
-//     //! \param[in] seg is the packet to either write or drop
-//     void write(TCPSegment &seg) {
-//         if (_should_drop(true)) {
-//             return;
-//         }
-//         return _adapter.write(seg);
-//     }
-
-//     //! \name
-//     //! Passthrough functions to the underlying AdapterT instance
-
-//     //!@{
-//     void set_listening(const bool l) { _adapter.set_listening(l); }      //!< FdAdapterBase::set_listening passthrough
-//     const FdAdapterConfig &config() const { return _adapter.config(); }  //!< FdAdapterBase::config passthrough
-//     FdAdapterConfig &config_mut() { return _adapter.config_mut(); }      //!< FdAdapterBase::config_mut passthrough
-//     void tick(const size_t ms_since_last_tick) {
-//         _adapter.tick(ms_since_last_tick);
-//     }  //!< FdAdapterBase::tick passthrough
-//     //!@}
-// };
-
-// #endif  // SPONGE_LIBSPONGE_LOSSY_FD_ADAPTER_HH
+impl<A: DatagramAdapter> DatagramAdapter for LossyFDAdaptor<A> {
+    fn fd(&self) -> NakedFileDescriptor {
+        self.adapter.fd()
+    }
+
+    fn read(&mut self) -> Option<TCPSegment> {
+        let seg = self.adapter.read();
+        if self.should_drop(false) {
+            return None;
+        }
+        seg
+    }
+
+    fn write(&mut self, seg: &mut TCPSegment) {
+        if self.should_drop(true) {
+            return;
+        }
+        self.adapter.write(seg)
+    }
+
+    fn set_listening(&mut self, l: bool) {
+        self.adapter.set_listening(l)
+    }
+
+    fn cfg(&self) -> &FDAdapterConfig {
+        self.adapter.cfg()
+    }
+
+    fn cfg_mut(&mut self) -> &mut FDAdapterConfig {
+        self.adapter.cfg_mut()
+    }
+
+    fn tick(&mut self, elapsed: Milliseconds) {
+        self.adapter.tick(elapsed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{LSSocket, NoneAdapter};
+
+    struct CountingAdapter {
+        socket: LSSocket<NoneAdapter>,
+        cfg: FDAdapterConfig,
+        written: usize,
+    }
+
+    impl CountingAdapter {
+        fn new(loss_rate_up: u16, loss_rate_dn: u16) -> Self {
+            let (socket, _) = LSSocket::try_pair().unwrap();
+            Self {
+                socket,
+                cfg: FDAdapterConfig {
+                    loss_rate_up,
+                    loss_rate_dn,
+                    ..Default::default()
+                },
+                written: 0,
+            }
+        }
+    }
+
+    impl DatagramAdapter for CountingAdapter {
+        fn fd(&self) -> NakedFileDescriptor {
+            (&self.socket).into()
+        }
+
+        fn read(&mut self) -> Option<TCPSegment> {
+            Some(TCPSegment::default())
+        }
+
+        fn write(&mut self, _seg: &mut TCPSegment) {
+            self.written += 1;
+        }
+
+        fn set_listening(&mut self, _l: bool) {}
+
+        fn cfg(&self) -> &FDAdapterConfig {
+            &self.cfg
+        }
+
+        fn cfg_mut(&mut self) -> &mut FDAdapterConfig {
+            &mut self.cfg
+        }
+
+        fn tick(&mut self, _elapsed: Milliseconds) {}
+    }
+
+    fn drop_pattern(adapter: &mut LossyFDAdaptor<CountingAdapter>) -> Vec<bool> {
+        (0..256).map(|_| adapter.read().is_none()).collect()
+    }
+
+    #[test]
+    fn test_no_loss() {
+        let mut adapter = LossyFDAdaptor::new(CountingAdapter::new(0, 0));
+        assert!(drop_pattern(&mut adapter).iter().all(|dropped| !dropped));
+        (0..256).for_each(|_| adapter.write(&mut TCPSegment::default()));
+        assert_eq!(adapter.adapter().written, 256);
+    }
+
+    #[test]
+    fn test_seeded_loss() {
+        let loss = u16::MAX / 2;
+        let mut first = LossyFDAdaptor::with_seed(CountingAdapter::new(loss, loss), 144);
+        let mut second = LossyFDAdaptor::with_seed(CountingAdapter::new(loss, loss), 144);
+        let pattern = drop_pattern(&mut first);
+        assert_eq!(pattern, drop_pattern(&mut second));
+
+        let dropped = pattern.iter().filter(|&&dropped| dropped).count();
+        assert!(dropped > 64 && dropped < 192);
+
+        (0..256).for_each(|_| first.write(&mut TCPSegment::default()));
+        assert!(first.adapter().written < 256);
+    }
+}
@@ -1,21 +1,31 @@
-use crate::{Address, WrappingU32};
+use crate::{Address, CongestionControlKind, WrappingU32};
+
+use anyhow::{Result, bail};
 
 #[derive(Debug, Clone)]
 pub struct TCPConfig {
     pub capacity: usize,
     pub max_payload_size: usize,
+    // the RTO before the first RTT sample, and the bounds the measured one is kept in
     pub timeout_default: u16,
+    pub min_rto: u16,
+    pub max_rto: u16,
     pub max_retx_attempts: u32,
     pub rt_timeout: u16,
     pub recv_capacity: usize,
     pub send_capacity: usize,
     pub fixed_isn: Option<WrappingU32>,
+    pub congestion_control: CongestionControlKind,
+    // seeds the congestion controller's randomness, for reproducible runs
+    pub rng_seed: Option<u64>,
 }
 
 impl TCPConfig {
     pub const DEFAULT_CAPACITY: usize = 64000;
     pub const MAX_PAYLOAD_SIZE: usize = 1452;
     pub const TIMEOUT_DFLT: u16 = 1000;
+    pub const MIN_RTO: u16 = 200;
+    pub const MAX_RTO: u16 = 60000;
     pub const MAX_RETX_ATTEMPTS: u32 = 8;
 }
 
@@ -25,11 +35,15 @@ impl Default for TCPConfig {
             capacity: Self::DEFAULT_CAPACITY,
             max_payload_size: Self::MAX_PAYLOAD_SIZE,
             timeout_default: Self::TIMEOUT_DFLT,
+            min_rto: Self::MIN_RTO,
+            max_rto: Self::MAX_RTO,
             max_retx_attempts: Self::MAX_RETX_ATTEMPTS,
             rt_timeout: Self::TIMEOUT_DFLT,
             recv_capacity: Self::DEFAULT_CAPACITY,
             send_capacity: Self::DEFAULT_CAPACITY,
             fixed_isn: None,
+            congestion_control: CongestionControlKind::default(),
+            rng_seed: None,
         }
     }
 }
@@ -41,3 +55,31 @@ pub struct FDAdapterConfig {
     pub loss_rate_dn: u16,
     pub loss_rate_up: u16,
 }
+
+impl FDAdapterConfig {
+    // a probability in 0..=1, scaled to the u16 the adapters compare against
+    pub fn parse_loss_rate(rate: &str) -> Result<u16> {
+        match rate.parse::<f64>() {
+            Ok(rate) if (0.0..=1.0).contains(&rate) => Ok((rate * f64::from(u16::MAX)) as u16),
+            _ => bail!("loss rate must be a number between 0 and 1, got {}", rate),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_loss_rate() {
+        assert_eq!(FDAdapterConfig::parse_loss_rate("0").unwrap(), 0);
+        assert_eq!(FDAdapterConfig::parse_loss_rate("1").unwrap(), u16::MAX);
+        assert_eq!(
+            FDAdapterConfig::parse_loss_rate("0.5").unwrap(),
+            u16::MAX / 2
+        );
+        for rate in ["-0.1", "1.5", "NaN", "lossy"] {
+            assert!(FDAdapterConfig::parse_loss_rate(rate).is_err());
+        }
+    }
+}
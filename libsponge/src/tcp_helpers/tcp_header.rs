@@ -1,7 +1,7 @@
 use std::fmt::{self, Debug, Display};
 
 use crate::{
-    WrappingU32,
+    TCPOption, WrappingU32,
     util::parser::{NetParser, NetUnparser, ParseError},
 };
 
@@ -21,6 +21,7 @@ pub struct TCPHeader {
     pub win: u16,
     pub check_sum: u16,
     urg_ptr: u16,
+    options: Vec<TCPOption>,
 }
 
 impl Default for TCPHeader {
@@ -40,6 +41,7 @@ impl Default for TCPHeader {
             win: 0,
             check_sum: 0,
             urg_ptr: 0,
+            options: Vec::new(),
         }
     }
 }
@@ -69,7 +71,20 @@ impl TCPHeader {
             return Err(ParseError::HeaderTooShort);
         }
 
-        p.remove_prefix(self.doff as usize * 4 - TCPHeader::LENGTH);
+        p.get_result()?;
+        self.options.clear();
+        let mut remaining = self.doff as usize * 4 - TCPHeader::LENGTH;
+        while remaining > 0 {
+            let option = TCPOption::parse(p, remaining)?;
+            remaining -= option.len();
+            let end = option == TCPOption::EOL;
+            self.options.push(option);
+            // whatever follows the end of the option list is padding
+            if end {
+                p.remove_prefix(remaining);
+                break;
+            }
+        }
 
         if p.is_err() {
             return p.get_result();
@@ -77,6 +92,27 @@ impl TCPHeader {
         Ok(())
     }
 
+    #[inline(always)]
+    pub fn options(&self) -> &[TCPOption] {
+        &self.options
+    }
+
+    // replaces the options, padding them out to a 4-byte boundary in `doff`
+    pub fn set_options(&mut self, options: Vec<TCPOption>) -> Result<(), ParseError> {
+        let len = options.iter().map(TCPOption::len).sum::<usize>();
+        if len > TCPOption::MAX_LENGTH {
+            return Err(ParseError::BadOptionLength);
+        }
+        self.doff = ((Self::LENGTH + len.next_multiple_of(4)) / 4) as u8;
+        self.options = options;
+        Ok(())
+    }
+
+    #[inline(always)]
+    pub fn length(&self) -> usize {
+        self.doff as usize * 4
+    }
+
     pub fn serialize(&self) -> Result<Vec<u8>, ParseError> {
         if self.doff < 5 {
             return Err(ParseError::HeaderTooShort);
@@ -100,7 +136,13 @@ impl TCPHeader {
         NetUnparser::u16(&mut buf, self.win); // window size
         NetUnparser::u16(&mut buf, self.check_sum); // checksum
         NetUnparser::u16(&mut buf, self.urg_ptr); // urgent pointer
-        buf.resize(Self::LENGTH, 0);
+        for option in &self.options {
+            option.serialize(&mut buf)?;
+        }
+        if buf.len() > self.length() {
+            return Err(ParseError::HeaderTooShort);
+        }
+        buf.resize(self.length(), 0); // pad with EOL
         Ok(buf)
     }
 }
@@ -119,6 +161,7 @@ impl PartialEq for TCPHeader {
             && self.win == other.win
             && self.check_sum == other.check_sum
             && self.urg_ptr == other.urg_ptr
+            && self.options == other.options
     }
 }
 
@@ -177,11 +220,114 @@ impl Debug for TCPHeader {
 }
 
 impl TCPHeader {
-    pub fn to_string(&self) -> String {
-        format!("{}", self)
-    }
-
     pub fn summary(&self) -> String {
         format!("{:?}", self)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Buffer;
+
+    fn parse(bytes: Vec<u8>) -> Result<TCPHeader, ParseError> {
+        let mut header = TCPHeader::default();
+        header.parse(&mut NetParser::new(Buffer::from(bytes)))?;
+        Ok(header)
+    }
+
+    fn with_options(options: &[u8]) -> Vec<u8> {
+        let header = TCPHeader {
+            doff: ((TCPHeader::LENGTH + options.len()) / 4) as u8,
+            ..Default::default()
+        };
+        let mut bytes = header.serialize().unwrap();
+        bytes.truncate(TCPHeader::LENGTH);
+        bytes.extend_from_slice(options);
+        bytes
+    }
+
+    #[test]
+    fn test_options_round_trip() {
+        let options = vec![
+            TCPOption::MSS(1460),
+            TCPOption::SackPermitted,
+            TCPOption::Timestamps { val: 1, ecr: 2 },
+            TCPOption::NOP,
+            TCPOption::WindowScale(7),
+            TCPOption::Sack(vec![(10.into(), 20.into()), (30.into(), 40.into())]),
+            TCPOption::Unknown(30, vec![0xab]),
+        ];
+        let mut header = TCPHeader {
+            syn: true,
+            win: 1000,
+            ..Default::default()
+        };
+        // 4 + 2 + 10 + 1 + 3 + 18 + 3 = 41 bytes, too many
+        assert!(matches!(
+            header.set_options(options.clone()),
+            Err(ParseError::BadOptionLength)
+        ));
+        assert!(header.options().is_empty());
+        assert_eq!(header.doff, 5);
+
+        header.set_options(options[..6].to_vec()).unwrap();
+        assert_eq!(header.doff, 5 + 10);
+        let bytes = header.serialize().unwrap();
+        assert_eq!(bytes.len(), 60);
+        // 38 bytes of options, padded with EOL
+        assert_eq!(&bytes[58..], &[0, 0]);
+
+        let parsed = parse(bytes).unwrap();
+        assert_eq!(parsed.doff, 15);
+        assert_eq!(parsed.options()[..6], options[..6]);
+        assert_eq!(parsed.options()[6], TCPOption::EOL);
+
+        header
+            .set_options(vec![TCPOption::Unknown(30, vec![0xab])])
+            .unwrap();
+        assert_eq!(header.doff, 6);
+        let bytes = header.serialize().unwrap();
+        assert_eq!(&bytes[20..], &[30, 3, 0xab, 0]);
+        assert_eq!(parse(bytes).unwrap().options().len(), 2);
+
+        header.set_options(Vec::new()).unwrap();
+        assert_eq!(header.serialize().unwrap().len(), TCPHeader::LENGTH);
+    }
+
+    #[test]
+    fn test_options_after_eol_are_padding() {
+        let header = parse(with_options(&[1, 0, 2, 4])).unwrap();
+        assert_eq!(header.options(), &[TCPOption::NOP, TCPOption::EOL]);
+    }
+
+    #[test]
+    fn test_malformed_options() {
+        let malformed: [&[u8]; 6] = [
+            &[2, 4, 5, 0xb4, 3, 0, 0, 0],
+            &[2, 3, 5, 0, 0, 0, 0, 0],
+            &[3, 3, 7, 4, 3, 0, 0, 0],
+            &[5, 2, 0, 0],
+            &[8, 10, 0, 0],
+            &[1, 1, 1, 30],
+        ];
+        for options in malformed {
+            assert!(matches!(
+                parse(with_options(options)),
+                Err(ParseError::BadOptionLength)
+            ));
+        }
+
+        let mut bytes = with_options(&[2, 4, 5, 0xb4]);
+        bytes.truncate(22);
+        assert!(matches!(parse(bytes), Err(ParseError::PacketTooShort)));
+
+        let mut header = TCPHeader::default();
+        header.set_options(vec![TCPOption::MSS(1460)]).unwrap();
+        header.doff = 5;
+        assert!(matches!(
+            header.serialize(),
+            Err(ParseError::HeaderTooShort)
+        ));
+    }
+}
@@ -0,0 +1,135 @@
+use crate::{
+    WrappingU32,
+    util::parser::{NetParser, NetUnparser, ParseError},
+};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TCPOption {
+    EOL,
+    NOP,
+    MSS(u16),
+    WindowScale(u8),
+    SackPermitted,
+    // (left edge, right edge) of each block
+    Sack(Vec<(WrappingU32, WrappingU32)>),
+    Timestamps { val: u32, ecr: u32 },
+    Unknown(u8, Vec<u8>),
+}
+
+impl TCPOption {
+    pub const KIND_EOL: u8 = 0;
+    pub const KIND_NOP: u8 = 1;
+    pub const KIND_MSS: u8 = 2;
+    pub const KIND_WINDOW_SCALE: u8 = 3;
+    pub const KIND_SACK_PERMITTED: u8 = 4;
+    pub const KIND_SACK: u8 = 5;
+    pub const KIND_TIMESTAMPS: u8 = 8;
+
+    // the longest option list that fits in a header (doff = 15)
+    pub const MAX_LENGTH: usize = 40;
+
+    pub fn kind(&self) -> u8 {
+        match self {
+            TCPOption::EOL => Self::KIND_EOL,
+            TCPOption::NOP => Self::KIND_NOP,
+            TCPOption::MSS(_) => Self::KIND_MSS,
+            TCPOption::WindowScale(_) => Self::KIND_WINDOW_SCALE,
+            TCPOption::SackPermitted => Self::KIND_SACK_PERMITTED,
+            TCPOption::Sack(_) => Self::KIND_SACK,
+            TCPOption::Timestamps { .. } => Self::KIND_TIMESTAMPS,
+            TCPOption::Unknown(kind, _) => *kind,
+        }
+    }
+
+    // bytes on the wire, including kind and length
+    #[allow(clippy::len_without_is_empty)]
+    pub fn len(&self) -> usize {
+        match self {
+            TCPOption::EOL | TCPOption::NOP => 1,
+            TCPOption::MSS(_) => 4,
+            TCPOption::WindowScale(_) => 3,
+            TCPOption::SackPermitted => 2,
+            TCPOption::Sack(blocks) => 2 + 8 * blocks.len(),
+            TCPOption::Timestamps { .. } => 10,
+            TCPOption::Unknown(_, data) => 2 + data.len(),
+        }
+    }
+
+    // parses one option out of the next `remaining` bytes of `p`
+    pub fn parse(p: &mut NetParser, remaining: usize) -> Result<Self, ParseError> {
+        let kind = p.parse_u8();
+        p.get_result()?;
+        match kind {
+            Self::KIND_EOL => return Ok(TCPOption::EOL),
+            Self::KIND_NOP => return Ok(TCPOption::NOP),
+            _ => {}
+        }
+
+        if remaining < 2 {
+            return Err(ParseError::BadOptionLength);
+        }
+        let len = p.parse_u8() as usize;
+        p.get_result()?;
+        if len < 2 || len > remaining {
+            return Err(ParseError::BadOptionLength);
+        }
+
+        let expected = match kind {
+            Self::KIND_MSS => len == 4,
+            Self::KIND_WINDOW_SCALE => len == 3,
+            Self::KIND_SACK_PERMITTED => len == 2,
+            Self::KIND_SACK => len > 2 && (len - 2).is_multiple_of(8),
+            Self::KIND_TIMESTAMPS => len == 10,
+            _ => true,
+        };
+        if !expected {
+            return Err(ParseError::BadOptionLength);
+        }
+
+        let option = match kind {
+            Self::KIND_MSS => TCPOption::MSS(p.parse_u16()),
+            Self::KIND_WINDOW_SCALE => TCPOption::WindowScale(p.parse_u8()),
+            Self::KIND_SACK_PERMITTED => TCPOption::SackPermitted,
+            Self::KIND_SACK => TCPOption::Sack(
+                (0..(len - 2) / 8)
+                    .map(|_| {
+                        let left = WrappingU32::new(p.parse_u32());
+                        (left, WrappingU32::new(p.parse_u32()))
+                    })
+                    .collect(),
+            ),
+            Self::KIND_TIMESTAMPS => TCPOption::Timestamps {
+                val: p.parse_u32(),
+                ecr: p.parse_u32(),
+            },
+            _ => TCPOption::Unknown(kind, (2..len).map(|_| p.parse_u8()).collect()),
+        };
+        p.get_result()?;
+        Ok(option)
+    }
+
+    pub fn serialize(&self, buf: &mut Vec<u8>) -> Result<(), ParseError> {
+        NetUnparser::u8(buf, self.kind());
+        if matches!(self, TCPOption::EOL | TCPOption::NOP) {
+            return Ok(());
+        }
+
+        let len = u8::try_from(self.len()).map_err(|_| ParseError::BadOptionLength)?;
+        NetUnparser::u8(buf, len);
+        match self {
+            TCPOption::MSS(mss) => NetUnparser::u16(buf, *mss),
+            TCPOption::WindowScale(shift) => NetUnparser::u8(buf, *shift),
+            TCPOption::Sack(blocks) => blocks.iter().for_each(|(left, right)| {
+                NetUnparser::u32(buf, left.raw_val());
+                NetUnparser::u32(buf, right.raw_val());
+            }),
+            TCPOption::Timestamps { val, ecr } => {
+                NetUnparser::u32(buf, *val);
+                NetUnparser::u32(buf, *ecr);
+            }
+            TCPOption::Unknown(_, data) => buf.extend_from_slice(data),
+            _ => {}
+        }
+        Ok(())
+    }
+}
@@ -1,8 +1,8 @@
-use crate::{Address, FDAdaptor, IPv4Header, IPv4NUM, InternetDatagram, TCPSegment};
+use crate::{Address, Buffer, FDAdaptor, IPv4Header, IPv4NUM, InternetDatagram, TCPSegment};
 
 pub trait ToI {}
 
-struct TCPOverIPv4;
+pub struct TCPOverIPv4;
 impl ToI for TCPOverIPv4 {}
 
 pub type TCPOverIPv4Adapter = FDAdaptor<TCPOverIPv4>;
@@ -16,10 +16,12 @@ impl TCPOverIPv4Adapter {
     pub fn unwrap_tcp_in_ip(&mut self, ip_dgram: &InternetDatagram) -> Option<TCPSegment> {
         let dgram_src = IPv4NUM(ip_dgram.header().src);
         let dgram_dst = IPv4NUM(ip_dgram.header().dst);
-        let cfg_src: IPv4NUM = (&self.cfg().source).try_into().ok()?;
-        let cfg_dst: IPv4NUM = (&self.cfg().destination).try_into().ok()?;
-        if !self.listen() && (dgram_dst != cfg_src || dgram_src != cfg_dst) {
-            return None;
+        if !self.listen() {
+            let cfg_src: IPv4NUM = (&self.cfg().source).try_into().ok()?;
+            let cfg_dst: IPv4NUM = (&self.cfg().destination).try_into().ok()?;
+            if dgram_dst != cfg_src || dgram_src != cfg_dst {
+                return None;
+            }
         }
         if ip_dgram.header().proto != IPv4Header::PROTO_TCP {
             return None;
@@ -28,14 +30,12 @@ impl TCPOverIPv4Adapter {
         let mut tcp_seg = TCPSegment::default();
         tcp_seg
             .parse(
-                ip_dgram.payload().try_into().ok()?,
+                Buffer::from(Into::<Vec<u8>>::into(ip_dgram.payload())),
                 ip_dgram.header().pseudo_cksum(),
             )
             .ok()?;
 
-        if IPv4NUM(tcp_seg.header().dst_port as u32) != cfg_src
-            || IPv4NUM(tcp_seg.header().src_port as u32) != cfg_dst
-        {
+        if tcp_seg.header().dst_port != self.cfg().source.port().ok()? {
             return None;
         }
 
@@ -44,7 +44,7 @@ impl TCPOverIPv4Adapter {
                 let cfg = self.cfg_mut();
                 cfg.source = Address::try_from_string(
                     &inet_ntoa(dgram_dst.0),
-                    cfg.source.ip().ok()?.as_str(),
+                    cfg.source.port().ok()?.to_string().as_str(),
                 )
                 .ok()?;
                 cfg.destination = Address::try_from_string(
@@ -58,7 +58,7 @@ impl TCPOverIPv4Adapter {
             }
         }
 
-        if tcp_seg.header().src_port != self.cfg_mut().destination.port().ok()? {
+        if tcp_seg.header().src_port != self.cfg().destination.port().ok()? {
             return None;
         }
 
@@ -77,7 +77,7 @@ impl TCPOverIPv4Adapter {
         ip_dgram.header_mut().dst = TryInto::<IPv4NUM>::try_into(dst_addr as &Address).ok()?.0;
 
         ip_dgram.header_mut().len = (ip_dgram.header().hlen as u16) * 4
-            + (tcp_seg.header().doff as u16) * 4
+            + tcp_seg.header().length() as u16
             + tcp_seg.payload().len() as u16;
 
         *ip_dgram.payload_mut() = tcp_seg.serialize(ip_dgram.header().pseudo_cksum()).ok()?;
@@ -85,3 +85,42 @@ impl TCPOverIPv4Adapter {
         Some(ip_dgram)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::FDAdapterConfig;
+
+    fn adapter(source: &str, destination: &str) -> TCPOverIPv4Adapter {
+        let mut adapter = TCPOverIPv4Adapter::default();
+        *adapter.cfg_mut() = FDAdapterConfig {
+            source: Address::try_from_string("169.254.144.9", source).unwrap(),
+            destination: Address::try_from_string("169.254.144.1", destination).unwrap(),
+            ..Default::default()
+        };
+        adapter
+    }
+
+    #[test]
+    fn test_wrap_and_unwrap() {
+        let mut client = adapter("40000", "80");
+        let mut seg = TCPSegment::default();
+        seg.header_mut().syn = true;
+        let dgram = client.wrap_tcp_in_ip(&mut seg).unwrap();
+        assert_eq!(dgram.header().src, 0xa9fe_9009);
+        assert_eq!(dgram.header().dst, 0xa9fe_9001);
+
+        // a listening server learns its peer from the SYN
+        let mut server = TCPOverIPv4Adapter::default();
+        server.cfg_mut().source = Address::try_from_string("0.0.0.0", "80").unwrap();
+        server.set_listening(true);
+        let seg = server.unwrap_tcp_in_ip(&dgram).unwrap();
+        assert!(seg.header().syn);
+        assert!(!server.listen());
+        assert_eq!(server.cfg().destination.to_string(), "169.254.144.9:40000");
+
+        // a connected adapter drops datagrams for other ports
+        let mut other = adapter("40001", "80");
+        assert!(other.unwrap_tcp_in_ip(&dgram).is_none());
+    }
+}
@@ -19,19 +19,19 @@ impl TCPSegment {
         }
 
         let mut p = NetParser::new(buffer);
-        self.header.parse(&mut p);
+        self.header.parse(&mut p)?;
         self.payload = p.buffer_mut().take();
         p.get_result()
     }
 
-    pub fn serialize(&mut self, datagram_layer_checksum: u32) -> Result<BufferList, ParseError> {
+    pub fn serialize(&self, datagram_layer_checksum: u32) -> Result<BufferList, ParseError> {
         let mut header_out = self.header.clone();
+        header_out.check_sum = 0;
         let mut check_sum = InternetChecksum::new(datagram_layer_checksum);
-        let hr_ser = header_out.serialize()?;
-        check_sum.add(&hr_ser);
-        check_sum.add(&self.payload.as_ref());
+        check_sum.add(&header_out.serialize()?);
+        check_sum.add(self.payload.as_ref());
         header_out.check_sum = check_sum.value();
-        Ok(vec![hr_ser.into(), self.payload_mut().take()].into())
+        Ok(vec![header_out.serialize()?.into(), self.payload.clone()].into())
     }
 
     pub fn header(&self) -> &TCPHeader {
@@ -54,3 +54,65 @@ impl TCPSegment {
         self.payload.len() + (self.header.syn as usize) + (self.header.fin as usize)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn segment() -> TCPSegment {
+        let mut seg = TCPSegment::default();
+        let header = seg.header_mut();
+        header.src_port = 1234;
+        header.dst_port = 80;
+        header.seq_no = 17.into();
+        header.syn = true;
+        header.win = 1000;
+        *seg.payload_mut() = Buffer::from(b"hello".to_vec());
+        seg
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let seg = segment();
+        let wire: Vec<u8> = (&seg.serialize(0x1234).unwrap()).into();
+        // serializing leaves the segment intact
+        assert_eq!(seg.payload().as_ref(), b"hello");
+        // and is stable
+        assert_eq!(Vec::<u8>::from(&seg.serialize(0x1234).unwrap()), wire);
+
+        let mut parsed = TCPSegment::default();
+        parsed.parse(Buffer::from(wire.clone()), 0x1234).unwrap();
+        let header = parsed.header();
+        assert_eq!((header.src_port, header.dst_port), (1234, 80));
+        assert_eq!(header.seq_no, 17.into());
+        assert!(header.syn && !header.ack);
+        assert_eq!(header.win, 1000);
+        assert_eq!(parsed.payload().as_ref(), b"hello");
+
+        // a checksum over the wrong pseudo-header doesn't verify
+        assert!(matches!(
+            TCPSegment::default().parse(Buffer::from(wire), 0),
+            Err(ParseError::BadChecksum)
+        ));
+    }
+
+    #[test]
+    fn test_bad_header() {
+        let mut seg = segment();
+        seg.header_mut().doff = 4;
+        assert!(matches!(seg.serialize(0), Err(ParseError::HeaderTooShort)));
+
+        let mut wire: Vec<u8> = (&segment().serialize(0).unwrap()).into();
+        wire[12] = 4 << 4;
+        // fix up the checksum so only the data offset is wrong
+        wire[16] = 0;
+        wire[17] = 0;
+        let mut check_sum = InternetChecksum::new(0);
+        check_sum.add(&wire);
+        wire[16..18].copy_from_slice(&check_sum.value().to_be_bytes());
+        assert!(matches!(
+            TCPSegment::default().parse(Buffer::from(wire), 0),
+            Err(ParseError::HeaderTooShort)
+        ));
+    }
+}
@@ -1,233 +1,658 @@
-use std::{sync::atomic::AtomicBool, thread};
+use crate::{
+    Address, DatagramAdapter, Direction, EthernetAddress, EventAction, EventLoop, EventResult,
+    FDAdapterConfig, LSSocket, LossyFDAdaptor, NakedFileDescriptor, NoneAdapter, TCPConfig,
+    TCPConnection, TCPOverIPv4OverEthernetAdapter, TCPOverIPv4OverTunFdAdapter, TCPOverUDPAdapter,
+    TCPState, TapFD, TunFD, random_port, timestamp_ms,
+};
 
-use anyhow::Result;
+use anyhow::{Result, anyhow};
+use libc::{SHUT_RDWR, SHUT_WR};
 
-use crate::{
-    EventLoop, EventRule, LSSocket, TCPConfig, TCPConnection, TCPOverIPv4OverEthernetAdapter,
-    TCPOverIPv4OverTunFdAdapter,
+use std::{
+    ops::{Deref, DerefMut},
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicBool, Ordering},
+    },
+    thread::{self, JoinHandle},
 };
 
-pub struct TCPSpongeSocket<A: Default + Clone> {
-    thread_data: LSSocket<A>,
+const TCP_TICK_MS: u64 = 10;
+
+struct TCPSpongeState<A> {
+    thread_data: LSSocket<NoneAdapter>,
     dgram_adapter: A,
-    tcp: Option<TCPConnection>,
-    event_loop: EventLoop,
-    // tcp_thread: thread,
-    abort: AtomicBool,
+    tcp: TCPConnection,
     inbound_shutdown: bool,
     outbound_shutdown: bool,
     fully_acked: bool,
 }
 
-impl<A: Default + Clone> TCPSpongeSocket<A> {
-    fn read_to_tcp(&mut self) -> Result<()> {
-        let mut rule = EventRule::new(self.dgram_adapter.into(), direction, handler);
-        // rule 1: read from filtered packet stream and dump into TCPConnection
-        //     _eventloop.add_rule(_datagram_adapter,
-        //                         Direction::In,
-        //                         [&] {
-        //                             auto seg = _datagram_adapter.read();
-        //                             if (seg) {
-        //                                 _tcp->segment_received(move(seg.value()));
-        //                             }
-
-        //                             // debugging output:
-        //                             if (_thread_data.eof() and _tcp.value().bytes_in_flight() == 0 and not _fully_acked) {
-        //                                 cerr << "DEBUG: Outbound stream to "
-        //                                      << _datagram_adapter.config().destination.to_string()
-        //                                      << " has been fully acknowledged.\n";
-        //                                 _fully_acked = true;
-        //                             }
-        //                         },
-        //                         [&] { return _tcp->active(); });
-
-        self.event_loop.add_rule(rule);
-    }
-
-    fn init_TCP(&mut self, cfg: &TCPConfig) -> Result<()> {
-        self.tcp = Some(TCPConnection::with_config(cfg)); // emplace?
-        self.event_loop.add_rule(rule);
-
-        //     // rule 2: read from pipe into outbound buffer
-        //     _eventloop.add_rule(
-        //         _thread_data,
-        //         Direction::In,
-        //         [&] {
-        //             const auto data = _thread_data.read(_tcp->remaining_outbound_capacity());
-        //             const auto len = data.size();
-        //             const auto amount_written = _tcp->write(move(data));
-        //             if (amount_written != len) {
-        //                 throw runtime_error("TCPConnection::write() accepted less than advertised length");
-        //             }
-
-        //             if (_thread_data.eof()) {
-        //                 _tcp->end_input_stream();
-        //                 _outbound_shutdown = true;
-
-        //                 // debugging output:
-        //                 cerr << "DEBUG: Outbound stream to " << _datagram_adapter.config().destination.to_string()
-        //                      << " finished (" << _tcp.value().bytes_in_flight() << " byte"
-        //                      << (_tcp.value().bytes_in_flight() == 1 ? "" : "s") << " still in flight).\n";
-        //             }
-        //         },
-        //         [&] { return (_tcp->active()) and (not _outbound_shutdown) and (_tcp->remaining_outbound_capacity() > 0); },
-        //         [&] {
-        //             _tcp->end_input_stream();
-        //             _outbound_shutdown = true;
-        //         });
-
-        //     // rule 3: read from inbound buffer into pipe
-        //     _eventloop.add_rule(
-        //         _thread_data,
-        //         Direction::Out,
-        //         [&] {
-        //             ByteStream &inbound = _tcp->inbound_stream();
-        //             // Write from the inbound_stream into
-        //             // the pipe, handling the possibility of a partial
-        //             // write (i.e., only pop what was actually written).
-        //             const size_t amount_to_write = min(size_t(65536), inbound.buffer_size());
-        //             const std::string buffer = inbound.peek_output(amount_to_write);
-        //             const auto bytes_written = _thread_data.write(move(buffer), false);
-        //             inbound.pop_output(bytes_written);
-
-        //             if (inbound.eof() or inbound.error()) {
-        //                 _thread_data.shutdown(SHUT_WR);
-        //                 _inbound_shutdown = true;
-
-        //                 // debugging output:
-        //                 cerr << "DEBUG: Inbound stream from " << _datagram_adapter.config().destination.to_string()
-        //                      << " finished " << (inbound.error() ? "with an error/reset.\n" : "cleanly.\n");
-        //                 if (_tcp.value().state() == TCPState::State::TIME_WAIT) {
-        //                     cerr << "DEBUG: Waiting for lingering segments (e.g. retransmissions of FIN) from peer...\n";
-        //                 }
-        //             }
-        //         },
-        //         [&] {
-        //             return (not _tcp->inbound_stream().buffer_empty()) or
-        //                    ((_tcp->inbound_stream().eof() or _tcp->inbound_stream().error()) and not _inbound_shutdown);
-        //         });
-
-        //     // rule 4: read outbound segments from TCPConnection and send as datagrams
-        //     _eventloop.add_rule(_datagram_adapter,
-        //                         Direction::Out,
-        //                         [&] {
-        //                             while (not _tcp->segments_out().empty()) {
-        //                                 _datagram_adapter.write(_tcp->segments_out().front());
-        //                                 _tcp->segments_out().pop();
-        //                             }
-        //                         },
-        //                         [&] { return not _tcp->segments_out().empty(); });
-        // }
+impl<A: DatagramAdapter> TCPSpongeState<A> {
+    // rule 1: read from filtered packet stream and dump into TCPConnection
+    fn read_datagram(&mut self) {
+        if let Some(seg) = self.dgram_adapter.read() {
+            self.tcp.segment_received(&seg);
+        }
+
+        if self.thread_data.eof() && self.tcp.bytes_in_flight() == 0 && !self.fully_acked {
+            eprintln!(
+                "DEBUG: Outbound stream to {} has been fully acknowledged.",
+                self.dgram_adapter.cfg().destination
+            );
+            self.fully_acked = true;
+        }
+    }
+
+    fn read_datagram_interest(&self) -> bool {
+        self.tcp.active()
     }
-}
 
-// //! Set up the TCPConnection and the event loop
-// void _initialize_TCP(const TCPConfig &config);
+    // rule 2: read from pipe into outbound buffer
+    fn read_owner(&mut self) {
+        let data = match self
+            .thread_data
+            .read(Some(self.tcp.remaining_outbound_capacity()))
+        {
+            Ok(data) => data,
+            Err(err) => return eprintln!("TCPSpongeSocket: {}", err),
+        };
+        let amount_written = self.tcp.write(&data);
+        assert_eq!(
+            amount_written,
+            data.len(),
+            "TCPConnection::write() accepted less than advertised length"
+        );
+
+        if self.thread_data.eof() {
+            self.tcp.end_input_stream();
+            self.outbound_shutdown = true;
+
+            let in_flight = self.tcp.bytes_in_flight();
+            eprintln!(
+                "DEBUG: Outbound stream to {} finished ({} byte{} still in flight).",
+                self.dgram_adapter.cfg().destination,
+                in_flight,
+                if in_flight == 1 { "" } else { "s" }
+            );
+        }
+    }
+
+    fn read_owner_interest(&self) -> bool {
+        self.tcp.active() && !self.outbound_shutdown && self.tcp.remaining_outbound_capacity() > 0
+    }
 
-// //! TCP state machine
-// std::optional<TCPConnection> _tcp{};
+    fn read_owner_cancel(&mut self) {
+        self.tcp.end_input_stream();
+        self.outbound_shutdown = true;
+    }
+
+    // rule 3: read from inbound buffer into pipe
+    fn write_owner(&mut self) {
+        let inbound = self.tcp.inbound_stream_mut();
+        // only pop what was actually written, the pipe may accept a partial write
+        let buffer = inbound.peek_out(65536.min(inbound.buffer_size()));
+        let bytes_written = match self.thread_data.write(buffer.as_slice(), false) {
+            Ok(bytes_written) => bytes_written,
+            Err(err) => return eprintln!("TCPSpongeSocket: {}", err),
+        };
+        self.tcp.inbound_stream_mut().pop_output(bytes_written);
+
+        let inbound = self.tcp.inbound_stream();
+        if inbound.eof() || inbound.error() {
+            let error = inbound.error();
+            if let Err(err) = self.thread_data.shutdown(SHUT_WR) {
+                eprintln!("TCPSpongeSocket: {}", err);
+            }
+            self.inbound_shutdown = true;
+
+            eprintln!(
+                "DEBUG: Inbound stream from {} finished {}",
+                self.dgram_adapter.cfg().destination,
+                if error {
+                    "with an error/reset."
+                } else {
+                    "cleanly."
+                }
+            );
+            if let Ok(TCPState::TimeWait) = self.tcp.renew_state() {
+                eprintln!(
+                    "DEBUG: Waiting for lingering segments (e.g. retransmissions of FIN) from peer..."
+                );
+            }
+        }
+    }
+
+    fn write_owner_interest(&self) -> bool {
+        let inbound = self.tcp.inbound_stream();
+        !inbound.buffer_empty() || ((inbound.eof() || inbound.error()) && !self.inbound_shutdown)
+    }
+
+    fn write_owner_cancel(&mut self) {
+        self.inbound_shutdown = true;
+    }
+
+    // rule 4: read outbound segments from TCPConnection and send as datagrams
+    fn write_datagrams(&mut self) {
+        while let Some(mut seg) = self.tcp.segments_out_mut().pop_front() {
+            self.dgram_adapter.write(&mut seg);
+        }
+    }
+
+    fn write_datagrams_interest(&self) -> bool {
+        !self.tcp.segments_out().is_empty()
+    }
+}
 
-// //! eventloop that handles all the events (new inbound datagram, new outbound bytes, new inbound bytes)
-// EventLoop _eventloop{};
+struct TCPSpongeThread<A> {
+    state: Arc<Mutex<TCPSpongeState<A>>>,
+    event_loop: EventLoop,
+    abort: Arc<AtomicBool>,
+}
 
-// //! Process events while specified condition is true
-// void _tcp_loop(const std::function<bool()> &condition);
+impl<A: DatagramAdapter + Send + 'static> TCPSpongeThread<A> {
+    fn add_rule(
+        &mut self,
+        fd: NakedFileDescriptor,
+        direction: Direction,
+        callback: fn(&mut TCPSpongeState<A>),
+        interest: fn(&TCPSpongeState<A>) -> bool,
+        cancel: fn(&mut TCPSpongeState<A>),
+    ) {
+        let (callback_state, interest_state, cancel_state) =
+            (self.state.clone(), self.state.clone(), self.state.clone());
+        self.event_loop.add_rule(
+            fd,
+            direction,
+            move || {
+                callback(&mut callback_state.lock().unwrap());
+                EventAction::Continue
+            },
+            move || interest(&interest_state.lock().unwrap()),
+            move || cancel(&mut cancel_state.lock().unwrap()),
+        );
+    }
 
-// //! Main loop of TCPConnection thread
-// void _tcp_main();
+    fn init_tcp(
+        thread_data: LSSocket<NoneAdapter>,
+        dgram_adapter: A,
+        cfg: &TCPConfig,
+        abort: Arc<AtomicBool>,
+    ) -> Self {
+        let adapter_fd = dgram_adapter.fd();
+        let thread_fd: NakedFileDescriptor = (&thread_data).into();
+        let state = TCPSpongeState {
+            thread_data,
+            dgram_adapter,
+            tcp: TCPConnection::with_config(cfg),
+            inbound_shutdown: false,
+            outbound_shutdown: false,
+            fully_acked: false,
+        };
+        let mut this = Self {
+            state: Arc::new(Mutex::new(state)),
+            event_loop: EventLoop::new(),
+            abort,
+        };
+
+        this.add_rule(
+            (&adapter_fd).into(),
+            Direction::In,
+            TCPSpongeState::read_datagram,
+            TCPSpongeState::read_datagram_interest,
+            |_| {},
+        );
+        this.add_rule(
+            (&thread_fd).into(),
+            Direction::In,
+            TCPSpongeState::read_owner,
+            TCPSpongeState::read_owner_interest,
+            TCPSpongeState::read_owner_cancel,
+        );
+        this.add_rule(
+            thread_fd,
+            Direction::Out,
+            TCPSpongeState::write_owner,
+            TCPSpongeState::write_owner_interest,
+            TCPSpongeState::write_owner_cancel,
+        );
+        this.add_rule(
+            adapter_fd,
+            Direction::Out,
+            TCPSpongeState::write_datagrams,
+            TCPSpongeState::write_datagrams_interest,
+            |_| {},
+        );
+        this
+    }
 
-// //! Handle to the TCPConnection thread; owner thread calls join() in the destructor
-// std::thread _tcp_thread{};
+    fn tcp_loop(&mut self, condition: impl Fn(&mut TCPSpongeState<A>) -> bool) {
+        let mut base_time = timestamp_ms();
+        while condition(&mut self.state.lock().unwrap()) {
+            match self.event_loop.wait_next_event(TCP_TICK_MS.into()) {
+                Ok(EventResult::Exit) => break,
+                Err(err) => {
+                    eprintln!("TCPSpongeSocket: event loop failed: {}", err);
+                    break;
+                }
+                _ => {}
+            }
+            if self.abort.load(Ordering::SeqCst) {
+                break;
+            }
+
+            let mut state = self.state.lock().unwrap();
+            if state.tcp.active() {
+                let next_time = timestamp_ms();
+                state.tcp.tick(next_time - base_time);
+                state.dgram_adapter.tick(next_time - base_time);
+                base_time = next_time;
+            }
+        }
+    }
 
-// //! Construct LocalStreamSocket fds from socket pair, initialize eventloop
-// TCPSpongeSocket(std::pair<FileDescriptor, FileDescriptor> data_socket_pair, AdaptT &&datagram_interface);
+    fn tcp_main(mut self) {
+        self.tcp_loop(|_| true);
+
+        let mut state = self.state.lock().unwrap();
+        if state.tcp.active() {
+            // an aborted connection tells the peer with a RST
+            state.tcp.reset();
+            state.write_datagrams();
+        } else {
+            eprintln!(
+                "DEBUG: TCP connection finished {}",
+                match state.tcp.renew_state() {
+                    Ok(TCPState::Reset) | Err(_) => "uncleanly.",
+                    _ => "cleanly.",
+                }
+            );
+        }
+        if let Err(err) = state.thread_data.shutdown(SHUT_RDWR) {
+            eprintln!("TCPSpongeSocket: {}", err);
+        }
+    }
+}
 
-// std::atomic_bool _abort{false};  //!< Flag used by the owner to force the TCPConnection thread to shut down
+pub struct TCPSpongeSocket<A> {
+    socket: LSSocket<NoneAdapter>,
+    thread_data: Option<LSSocket<NoneAdapter>>,
+    dgram_adapter: Option<A>,
+    tcp_thread: Option<JoinHandle<()>>,
+    abort: Arc<AtomicBool>,
+}
 
-// bool _inbound_shutdown{false};  //!< Has TCPSpongeSocket shut down the incoming data to the owner?
+impl<A> Deref for TCPSpongeSocket<A> {
+    type Target = LSSocket<NoneAdapter>;
 
-// bool _outbound_shutdown{false};  //!< Has the owner shut down the outbound data to the TCP connection?
+    fn deref(&self) -> &LSSocket<NoneAdapter> {
+        &self.socket
+    }
+}
 
-// bool _fully_acked{false};  //!< Has the outbound data been fully acknowledged by the peer?
+impl<A> DerefMut for TCPSpongeSocket<A> {
+    fn deref_mut(&mut self) -> &mut LSSocket<NoneAdapter> {
+        &mut self.socket
+    }
+}
 
-// public:
-// //! Construct from the interface that the TCPConnection thread will use to read and write datagrams
-// explicit TCPSpongeSocket(AdaptT &&datagram_interface);
+impl<A: DatagramAdapter + Send + 'static> TCPSpongeSocket<A> {
+    fn start_tcp(
+        &mut self,
+        c_tcp: &TCPConfig,
+        c_ad: FDAdapterConfig,
+    ) -> Result<TCPSpongeThread<A>> {
+        let (Some(thread_data), Some(mut dgram_adapter)) =
+            (self.thread_data.take(), self.dgram_adapter.take())
+        else {
+            return Err(anyhow!("TCPSpongeSocket has already been used"));
+        };
+        *dgram_adapter.cfg_mut() = c_ad;
+        Ok(TCPSpongeThread::init_tcp(
+            thread_data,
+            dgram_adapter,
+            c_tcp,
+            self.abort.clone(),
+        ))
+    }
 
-// //! Close socket, and wait for TCPConnection to finish
-// //! \note Calling this function is only advisable if the socket has reached EOF,
-// //! or else may wait foreever for remote peer to close the TCP connection.
-// void wait_until_closed();
+    pub fn new(dgram_adapter: A) -> Result<Self> {
+        let (socket, mut thread_data) = LSSocket::try_pair()?;
+        thread_data.set_blocking(false)?;
+        Ok(Self {
+            socket,
+            thread_data: Some(thread_data),
+            dgram_adapter: Some(dgram_adapter),
+            tcp_thread: None,
+            abort: Arc::new(AtomicBool::new(false)),
+        })
+    }
 
-// //! Connect using the specified configurations; blocks until connect succeeds or fails
-// void connect(const TCPConfig &c_tcp, const FdAdapterConfig &c_ad);
+    pub fn connect(&mut self, c_tcp: &TCPConfig, c_ad: FDAdapterConfig) -> Result<()> {
+        let destination = c_ad.destination.clone();
+        let mut tcp_thread = self.start_tcp(c_tcp, c_ad)?;
+
+        eprintln!("DEBUG: Connecting to {}...", destination);
+        {
+            let mut state = tcp_thread.state.lock().unwrap();
+            state.tcp.connect();
+            if !matches!(state.tcp.renew_state(), Ok(TCPState::SynSent)) {
+                return Err(anyhow!(
+                    "After TCPConnection::connect(), state was not SynSent"
+                ));
+            }
+        }
 
-// //! Listen and accept using the specified configurations; blocks until accept succeeds or fails
-// void listen_and_accept(const TCPConfig &c_tcp, const FdAdapterConfig &c_ad);
+        tcp_thread.tcp_loop(|state| matches!(state.tcp.renew_state(), Ok(TCPState::SynSent)));
+        if !matches!(
+            tcp_thread.state.lock().unwrap().tcp.renew_state(),
+            Ok(TCPState::Established)
+        ) {
+            return Err(anyhow!("Failed to connect to {}", destination));
+        }
+        eprintln!("Successfully connected to {}.", destination);
 
-// //! When a connected socket is destructed, it will send a RST
-// ~TCPSpongeSocket();
+        self.tcp_thread = Some(thread::spawn(move || tcp_thread.tcp_main()));
+        Ok(())
+    }
 
-// //! \name
-// //! This object cannot be safely moved or copied, since it is in use by two threads simultaneously
+    pub fn listen_and_accept(&mut self, c_tcp: &TCPConfig, c_ad: FDAdapterConfig) -> Result<()> {
+        if let Some(dgram_adapter) = self.dgram_adapter.as_mut() {
+            dgram_adapter.set_listening(true);
+        }
+        let mut tcp_thread = self.start_tcp(c_tcp, c_ad)?;
+
+        eprintln!("DEBUG: Listening for incoming connection...");
+        tcp_thread.tcp_loop(|state| {
+            matches!(
+                state.tcp.renew_state(),
+                Ok(TCPState::Listen | TCPState::SynRcvd | TCPState::SynSent)
+            )
+        });
+        {
+            let mut state = tcp_thread.state.lock().unwrap();
+            if !matches!(state.tcp.renew_state(), Ok(TCPState::Established)) {
+                return Err(anyhow!("Failed to accept an incoming connection"));
+            }
+            eprintln!(
+                "New connection from {}.",
+                state.dgram_adapter.cfg().destination
+            );
+        }
 
-// //!@{
-// TCPSpongeSocket(const TCPSpongeSocket &) = delete;
-// TCPSpongeSocket(TCPSpongeSocket &&) = delete;
-// TCPSpongeSocket &operator=(const TCPSpongeSocket &) = delete;
-// TCPSpongeSocket &operator=(TCPSpongeSocket &&) = delete;
-// //!@}
+        self.tcp_thread = Some(thread::spawn(move || tcp_thread.tcp_main()));
+        Ok(())
+    }
 
-// //! \name
-// //! Some methods of the parent Socket wouldn't work as expected on the TCP socket, so delete them
+    pub fn wait_until_closed(&mut self) -> Result<()> {
+        self.socket.shutdown(SHUT_RDWR)?;
+        if let Some(tcp_thread) = self.tcp_thread.take() {
+            eprint!("DEBUG: Waiting for clean shutdown... ");
+            tcp_thread
+                .join()
+                .map_err(|_| anyhow!("TCPConnection thread panicked"))?;
+            eprintln!("done.");
+        }
+        Ok(())
+    }
+}
 
-// //!@{
-// void bind(const Address &address) = delete;
-// Address local_address() const = delete;
-// Address peer_address() const = delete;
-// void set_reuseaddr() = delete;
-// //!@}
-// };
+impl<A> Drop for TCPSpongeSocket<A> {
+    fn drop(&mut self) {
+        if let Some(tcp_thread) = self.tcp_thread.take() {
+            eprintln!("Warning: unclean shutdown of TCPSpongeSocket");
+            // force the other side to exit
+            self.abort.store(true, Ordering::SeqCst);
+            if tcp_thread.join().is_err() {
+                eprintln!("TCPConnection thread panicked");
+            }
+        }
+    }
+}
 
 pub type TCPOverIPv4SpongeSocket = TCPSpongeSocket<TCPOverIPv4OverTunFdAdapter>;
 
+impl TCPOverIPv4SpongeSocket {
+    const TUN_DFLT: &str = "tun144";
+    const LOCAL_ADDRESS_DFLT: &str = "169.254.144.9";
+
+    pub fn try_default() -> Result<Self> {
+        Self::new(TCPOverIPv4OverTunFdAdapter::new(TunFD::try_new(
+            Self::TUN_DFLT,
+        )?))
+    }
+
+    pub fn connect_to(&mut self, address: &Address) -> Result<()> {
+        let multiplexer_cfg = FDAdapterConfig {
+            source: Address::try_from_string(
+                Self::LOCAL_ADDRESS_DFLT,
+                random_port().to_string().as_str(),
+            )?,
+            destination: address.clone(),
+            ..Default::default()
+        };
+        self.connect(&TCPConfig::default(), multiplexer_cfg)
+    }
+}
+
+pub type LossyTCPOverIPv4SpongeSocket =
+    TCPSpongeSocket<LossyFDAdaptor<TCPOverIPv4OverTunFdAdapter>>;
+
 pub type RS144TCPSocket = TCPOverIPv4SpongeSocket;
 
+pub type TCPOverUDPSpongeSocket = TCPSpongeSocket<TCPOverUDPAdapter>;
+
+pub type LossyTCPOverUDPSpongeSocket = TCPSpongeSocket<LossyFDAdaptor<TCPOverUDPAdapter>>;
+
 pub type TCPOverIPv4OverEthernetSpongeSocket = TCPSpongeSocket<TCPOverIPv4OverEthernetAdapter>;
 
-#[derive(Default)]
+pub type LossyTCPOverIPv4OverEthernetSpongeSocket =
+    TCPSpongeSocket<LossyFDAdaptor<TCPOverIPv4OverEthernetAdapter>>;
+
 pub struct FullStackSocket {
-    eof_flag: bool,
+    socket: TCPOverIPv4OverEthernetSpongeSocket,
+}
+
+impl Deref for FullStackSocket {
+    type Target = TCPOverIPv4OverEthernetSpongeSocket;
+
+    fn deref(&self) -> &TCPOverIPv4OverEthernetSpongeSocket {
+        &self.socket
+    }
+}
+
+impl DerefMut for FullStackSocket {
+    fn deref_mut(&mut self) -> &mut TCPOverIPv4OverEthernetSpongeSocket {
+        &mut self.socket
+    }
 }
 
 impl FullStackSocket {
-    pub fn new() -> Self {
-        FullStackSocket {
-            ..Default::default()
-        }
+    const TAP_DFLT: &str = "tap10";
+    const LOCAL_TAP_IP_ADDRESS: &str = "169.254.10.9";
+
+    pub fn try_new(gateway: &str) -> Result<Self> {
+        let adapter = TCPOverIPv4OverEthernetAdapter::new(
+            TapFD::try_new(Self::TAP_DFLT)?,
+            EthernetAddress::random_private(),
+            Address::try_from_string(Self::LOCAL_TAP_IP_ADDRESS, "0")?,
+            Address::try_from_string(gateway, "0")?,
+        );
+        Ok(Self {
+            socket: TCPSpongeSocket::new(adapter)?,
+        })
     }
 
-    pub fn connect(&self, addr: &str) -> Result<(), String> {
-        // Implementation goes here
-        Ok(())
+    pub fn connect(&mut self, addr: &str) -> Result<()> {
+        let (host, port) = addr
+            .rsplit_once(':')
+            .ok_or_else(|| anyhow!("expected an address of the form host:port, got {}", addr))?;
+        let multiplexer_cfg = FDAdapterConfig {
+            source: Address::try_from_string(
+                Self::LOCAL_TAP_IP_ADDRESS,
+                random_port().to_string().as_str(),
+            )?,
+            destination: Address::try_from_hostname(host, port)?,
+            ..Default::default()
+        };
+        self.socket.connect(&TCPConfig::default(), multiplexer_cfg)
     }
 
-    pub fn write(&self, data: &[u8]) -> Result<(), String> {
-        // Implementation goes here
+    pub fn write(&mut self, data: &[u8]) -> Result<()> {
+        self.socket.write(data, true)?;
         Ok(())
     }
 
-    pub fn read(&self) -> Result<u8, String> {
-        // Implementation goes here
-        Ok(0)
+    pub fn read(&mut self) -> Result<Vec<u8>> {
+        Ok(self.socket.read(None)?)
     }
 
-    pub fn wait_until_closed(&self) -> Result<(), String> {
-        // Implementation goes here
-        Ok(())
+    pub fn wait_until_closed(&mut self) -> Result<()> {
+        self.socket.wait_until_closed()
     }
 
     pub fn eof(&self) -> bool {
-        self.eof_flag
+        self.socket.eof()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Buffer, Milliseconds, TCPSegment, UDPSocket};
+
+    use libc::{AF_UNIX, SOCK_DGRAM, socketpair};
+
+    // carries serialized segments over one end of a datagram socketpair
+    struct PairAdapter {
+        fd: NakedFileDescriptor,
+        cfg: FDAdapterConfig,
+    }
+
+    impl PairAdapter {
+        fn pair() -> (Self, Self) {
+            let mut fds = [0; 2];
+            assert_eq!(
+                unsafe { socketpair(AF_UNIX, SOCK_DGRAM, 0, fds.as_mut_ptr()) },
+                0
+            );
+            let adapter = |fd| PairAdapter {
+                fd: NakedFileDescriptor::from(fd),
+                cfg: FDAdapterConfig::default(),
+            };
+            (adapter(fds[0]), adapter(fds[1]))
+        }
+    }
+
+    impl DatagramAdapter for PairAdapter {
+        fn fd(&self) -> NakedFileDescriptor {
+            (&self.fd).into()
+        }
+
+        fn read(&mut self) -> Option<TCPSegment> {
+            let mut seg = TCPSegment::default();
+            let data = self.fd.read(None).ok()?;
+            seg.parse(Buffer::from(data), 0).ok()?;
+            Some(seg)
+        }
+
+        fn write(&mut self, seg: &mut TCPSegment) {
+            let data: Vec<u8> = (&seg.serialize(0).unwrap()).into();
+            self.fd.write(data.as_slice(), true).unwrap();
+        }
+
+        fn set_listening(&mut self, _l: bool) {}
+
+        fn cfg(&self) -> &FDAdapterConfig {
+            &self.cfg
+        }
+
+        fn cfg_mut(&mut self) -> &mut FDAdapterConfig {
+            &mut self.cfg
+        }
+
+        fn tick(&mut self, _elapsed: Milliseconds) {}
+    }
+
+    fn read_to_end(socket: &mut LSSocket<NoneAdapter>) -> Vec<u8> {
+        let mut data = Vec::new();
+        while !socket.eof() {
+            data.extend(socket.read(None).unwrap());
+        }
+        data
+    }
+
+    #[test]
+    fn test_listen_and_accept() {
+        let c_tcp = TCPConfig {
+            rt_timeout: 100,
+            ..Default::default()
+        };
+        let (server_adapter, client_adapter) = PairAdapter::pair();
+
+        let mut server = TCPSpongeSocket::new(server_adapter).unwrap();
+        let server_tcp = c_tcp.clone();
+        let server_thread = thread::spawn(move || {
+            server
+                .listen_and_accept(&server_tcp, FDAdapterConfig::default())
+                .unwrap();
+            let request = read_to_end(&mut server);
+            server.write(b"pong".as_slice(), true).unwrap();
+            server.shutdown(SHUT_WR).unwrap();
+            server.wait_until_closed().unwrap();
+            request
+        });
+
+        let mut client = TCPSpongeSocket::new(client_adapter).unwrap();
+        client.connect(&c_tcp, FDAdapterConfig::default()).unwrap();
+        client.write(b"ping".as_slice(), true).unwrap();
+        client.shutdown(SHUT_WR).unwrap();
+        assert_eq!(read_to_end(&mut client), b"pong");
+        client.wait_until_closed().unwrap();
+
+        assert_eq!(server_thread.join().unwrap(), b"ping");
+    }
+
+    #[test]
+    fn test_tcp_over_udp() {
+        let c_tcp = TCPConfig {
+            rt_timeout: 100,
+            ..Default::default()
+        };
+
+        let mut server_udp = UDPSocket::try_default().unwrap();
+        server_udp
+            .bind(&Address::try_from_string("127.0.0.1", "0").unwrap())
+            .unwrap();
+        let server_addr = server_udp.local_addr().unwrap();
+        let mut server = TCPOverUDPSpongeSocket::new(TCPOverUDPAdapter::new(server_udp)).unwrap();
+        let server_cfg = FDAdapterConfig {
+            source: server_addr.clone(),
+            ..Default::default()
+        };
+        let server_tcp = c_tcp.clone();
+        let server_thread = thread::spawn(move || {
+            server.listen_and_accept(&server_tcp, server_cfg).unwrap();
+            let request = read_to_end(&mut server);
+            server.write(b"pong".as_slice(), true).unwrap();
+            server.shutdown(SHUT_WR).unwrap();
+            server.wait_until_closed().unwrap();
+            request
+        });
+
+        let client_udp = UDPSocket::try_default().unwrap();
+        let mut client = TCPOverUDPSpongeSocket::new(TCPOverUDPAdapter::new(client_udp)).unwrap();
+        let client_cfg = FDAdapterConfig {
+            source: Address::try_from_string("127.0.0.1", "0").unwrap(),
+            destination: server_addr,
+            ..Default::default()
+        };
+        client.connect(&c_tcp, client_cfg).unwrap();
+        client.write(b"ping".as_slice(), true).unwrap();
+        client.shutdown(SHUT_WR).unwrap();
+        assert_eq!(read_to_end(&mut client), b"pong");
+        client.wait_until_closed().unwrap();
+
+        assert_eq!(server_thread.join().unwrap(), b"ping");
     }
 }
@@ -1,17 +1,204 @@
-use crate::{FDAdaptor, ToI};
+use crate::{
+    Address, Buffer, DatagramAdapter, EthernetAddress, EthernetFrame, FDAdapterConfig,
+    InternetDatagram, Milliseconds, NakedFileDescriptor, NetworkInterface, NoneAdapter,
+    NoneProtocol, TCPOverIPv4Adapter, TCPSegment, TapFD, TunFD,
+};
 
-trait ToIoT: ToI {}
-trait ToIoE: ToI {}
+use std::ops::{Deref, DerefMut};
 
-impl ToI for TCPOverIPv4OverTUN {}
-impl ToI for TCPOverIPv4OverEthernet {}
+pub struct TCPOverIPv4OverTunFdAdapter {
+    adapter: TCPOverIPv4Adapter,
+    tun: TunFD<NoneAdapter, NoneProtocol>,
+}
 
-pub struct TCPOverIPv4OverTUN;
-impl ToIoT for TCPOverIPv4OverTUN {}
+impl Deref for TCPOverIPv4OverTunFdAdapter {
+    type Target = TCPOverIPv4Adapter;
 
-pub struct TCPOverIPv4OverEthernet;
-impl ToIoE for TCPOverIPv4OverEthernet {}
+    fn deref(&self) -> &TCPOverIPv4Adapter {
+        &self.adapter
+    }
+}
 
-pub type TCPOverIPv4OverTunFdAdapter = FDAdaptor<TCPOverIPv4OverTUN>;
+impl DerefMut for TCPOverIPv4OverTunFdAdapter {
+    fn deref_mut(&mut self) -> &mut TCPOverIPv4Adapter {
+        &mut self.adapter
+    }
+}
 
-pub type TCPOverIPv4OverEthernetAdapter = FDAdaptor<TCPOverIPv4OverEthernet>;
+impl TCPOverIPv4OverTunFdAdapter {
+    pub fn new(tun: TunFD<NoneAdapter, NoneProtocol>) -> Self {
+        Self {
+            adapter: TCPOverIPv4Adapter::default(),
+            tun,
+        }
+    }
+
+    #[inline(always)]
+    pub fn tun(&self) -> &TunFD<NoneAdapter, NoneProtocol> {
+        &self.tun
+    }
+
+    #[inline(always)]
+    pub fn tun_mut(&mut self) -> &mut TunFD<NoneAdapter, NoneProtocol> {
+        &mut self.tun
+    }
+}
+
+impl DatagramAdapter for TCPOverIPv4OverTunFdAdapter {
+    fn fd(&self) -> NakedFileDescriptor {
+        (&self.tun).into()
+    }
+
+    fn read(&mut self) -> Option<TCPSegment> {
+        let data = match self.tun.read(None) {
+            Ok(data) => data,
+            Err(err) => {
+                eprintln!("TCPOverIPv4OverTunFdAdapter: {}", err);
+                return None;
+            }
+        };
+        let mut ip_dgram = InternetDatagram::default();
+        ip_dgram.try_parse(Buffer::from(data)).ok()?;
+        self.adapter.unwrap_tcp_in_ip(&ip_dgram)
+    }
+
+    fn write(&mut self, seg: &mut TCPSegment) {
+        let Some(ip_dgram) = self.adapter.wrap_tcp_in_ip(seg) else {
+            return eprintln!("TCPOverIPv4OverTunFdAdapter: failed to wrap segment");
+        };
+        let result = ip_dgram
+            .try_serialize()
+            .map_err(anyhow::Error::from)
+            .and_then(|payload| self.tun.write(&payload, true));
+        if let Err(err) = result {
+            eprintln!("TCPOverIPv4OverTunFdAdapter: {}", err);
+        }
+    }
+
+    fn set_listening(&mut self, l: bool) {
+        self.adapter.set_listening(l)
+    }
+
+    fn cfg(&self) -> &FDAdapterConfig {
+        self.adapter.cfg()
+    }
+
+    fn cfg_mut(&mut self) -> &mut FDAdapterConfig {
+        self.adapter.cfg_mut()
+    }
+
+    fn tick(&mut self, elapsed: Milliseconds) {
+        self.adapter.tick(elapsed)
+    }
+}
+
+pub struct TCPOverIPv4OverEthernetAdapter {
+    adapter: TCPOverIPv4Adapter,
+    tap: TapFD<NoneAdapter, NoneProtocol>,
+    interface: NetworkInterface,
+    next_hop: Address,
+}
+
+impl Deref for TCPOverIPv4OverEthernetAdapter {
+    type Target = TCPOverIPv4Adapter;
+
+    fn deref(&self) -> &TCPOverIPv4Adapter {
+        &self.adapter
+    }
+}
+
+impl DerefMut for TCPOverIPv4OverEthernetAdapter {
+    fn deref_mut(&mut self) -> &mut TCPOverIPv4Adapter {
+        &mut self.adapter
+    }
+}
+
+impl TCPOverIPv4OverEthernetAdapter {
+    fn send_pending(&mut self) {
+        while let Some(mut frame) = self.interface.frames_out_mut().pop_front() {
+            if let Err(err) = self.tap.write(&frame.serialize(), true) {
+                eprintln!("TCPOverIPv4OverEthernetAdapter: {}", err);
+            }
+        }
+    }
+}
+
+impl TCPOverIPv4OverEthernetAdapter {
+    pub fn new(
+        tap: TapFD<NoneAdapter, NoneProtocol>,
+        ethernet_address: EthernetAddress,
+        ip_address: Address,
+        next_hop: Address,
+    ) -> Self {
+        Self {
+            adapter: TCPOverIPv4Adapter::default(),
+            tap,
+            interface: NetworkInterface::new(ethernet_address, ip_address),
+            next_hop,
+        }
+    }
+
+    #[inline(always)]
+    pub fn tap(&self) -> &TapFD<NoneAdapter, NoneProtocol> {
+        &self.tap
+    }
+
+    #[inline(always)]
+    pub fn interface(&self) -> &NetworkInterface {
+        &self.interface
+    }
+
+    #[inline(always)]
+    pub fn interface_mut(&mut self) -> &mut NetworkInterface {
+        &mut self.interface
+    }
+}
+
+impl DatagramAdapter for TCPOverIPv4OverEthernetAdapter {
+    fn fd(&self) -> NakedFileDescriptor {
+        (&self.tap).into()
+    }
+
+    fn read(&mut self) -> Option<TCPSegment> {
+        let data = match self.tap.read(None) {
+            Ok(data) => data,
+            Err(err) => {
+                eprintln!("TCPOverIPv4OverEthernetAdapter: {}", err);
+                return None;
+            }
+        };
+        let mut frame = EthernetFrame::default();
+        frame.parse(Buffer::from(data)).ok()?;
+
+        // the incoming frame may have made the interface reply to an ARP request
+        let ip_dgram = self.interface.recv_frame(&frame);
+        self.send_pending();
+        self.adapter.unwrap_tcp_in_ip(&ip_dgram?)
+    }
+
+    fn write(&mut self, seg: &mut TCPSegment) {
+        let Some(ip_dgram) = self.adapter.wrap_tcp_in_ip(seg) else {
+            return eprintln!("TCPOverIPv4OverEthernetAdapter: failed to wrap segment");
+        };
+        self.interface.send_datagram(&ip_dgram, &self.next_hop);
+        self.send_pending();
+    }
+
+    fn set_listening(&mut self, l: bool) {
+        self.adapter.set_listening(l)
+    }
+
+    fn cfg(&self) -> &FDAdapterConfig {
+        self.adapter.cfg()
+    }
+
+    fn cfg_mut(&mut self) -> &mut FDAdapterConfig {
+        self.adapter.cfg_mut()
+    }
+
+    fn tick(&mut self, elapsed: Milliseconds) {
+        self.adapter.tick(elapsed);
+        self.interface.tick(elapsed);
+        self.send_pending();
+    }
+}
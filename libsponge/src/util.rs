@@ -1,3 +1,6 @@
+pub mod bidirectional_stream_copy;
+pub use bidirectional_stream_copy::*;
+
 pub mod address;
 pub use address::*;
 
@@ -19,5 +22,6 @@ pub use socket::*;
 pub mod tun;
 pub use tun::*;
 
+#[allow(clippy::module_inception)]
 pub mod util;
 pub use util::*;
@@ -1,12 +1,18 @@
 use anyhow::{Error, Ok, Result};
 use libc::{
-    AF_INET, AI_ALL, AI_NUMERICHOST, AI_NUMERICSERV, addrinfo, freeaddrinfo, getaddrinfo,
-    getnameinfo, in_addr, sockaddr, sockaddr_in, sockaddr_storage, socklen_t,
+    AF_INET, AI_ALL, AI_NUMERICHOST, AI_NUMERICSERV, NI_NUMERICHOST, NI_NUMERICSERV, addrinfo,
+    freeaddrinfo, getaddrinfo, getnameinfo, in_addr, sockaddr, sockaddr_in, sockaddr_storage,
+    socklen_t,
 };
 
 use crate::TaggedError;
 
-use std::{mem::zeroed, ptr::null_mut};
+use std::{
+    ffi::{CStr, CString},
+    fmt::{self, Display, Formatter},
+    mem::zeroed,
+    ptr::null_mut,
+};
 
 struct GAIError(String);
 
@@ -16,19 +22,20 @@ impl From<String> for GAIError {
     }
 }
 
-impl Into<String> for GAIError {
-    fn into(self) -> String {
-        format!("GAIError: {}", self.0)
+impl From<GAIError> for String {
+    fn from(val: GAIError) -> Self {
+        format!("GAIError: {}", val.0)
     }
 }
 
+#[derive(Clone, Copy)]
 pub struct RawAddr {
     pub storage: sockaddr_storage,
 }
 
 impl PartialEq for RawAddr {
     fn eq(&self, other: &Self) -> bool {
-        self.storage.ss_family == other.storage.ss_family
+        self.as_bytes() == other.as_bytes()
     }
 }
 
@@ -49,6 +56,15 @@ impl From<sockaddr_storage> for RawAddr {
 }
 
 impl RawAddr {
+    fn as_bytes(&self) -> &[u8] {
+        unsafe {
+            std::slice::from_raw_parts(
+                &self.storage as *const _ as *const u8,
+                size_of::<sockaddr_storage>(),
+            )
+        }
+    }
+
     pub fn as_ptr(&self) -> *const sockaddr {
         &self.storage as *const _ as *const sockaddr
     }
@@ -58,7 +74,7 @@ impl RawAddr {
     }
 }
 
-#[derive(PartialEq, Eq, Default)]
+#[derive(PartialEq, Eq, Default, Clone)]
 pub struct Address {
     pub size: socklen_t,
     addr: RawAddr,
@@ -67,10 +83,12 @@ pub struct Address {
 impl Address {
     fn try_from_node(node: &str, service: &str, hints: &addrinfo) -> Result<Self> {
         let mut resolved_address = null_mut();
+        let node = CString::new(node)?;
+        let service = CString::new(service)?;
         let gai_ret = unsafe {
             getaddrinfo(
-                node as *const str as *const i8,
-                service as *const str as *const i8,
+                node.as_ptr(),
+                service.as_ptr(),
                 hints,
                 &mut resolved_address,
             )
@@ -94,13 +112,13 @@ impl Address {
         }
         let _guard = AddrInfoGuard(resolved_address);
 
+        // ai_addr only holds ai_addrlen bytes, so copy just those
         unsafe {
-            let addr = (*resolved_address).ai_addr as *mut sockaddr_storage;
-            let size = (*resolved_address).ai_addrlen;
-            Ok(Self {
-                size,
-                addr: RawAddr { storage: *addr },
-            })
+            let resolved = &*resolved_address;
+            Self::try_from((
+                resolved.ai_addr as *const sockaddr,
+                resolved.ai_addrlen as usize,
+            ))
         }
     }
 }
@@ -163,7 +181,7 @@ impl Address {
         }
     }
 
-    pub fn ip_port(&mut self) -> Result<(String, u16)> {
+    pub fn ip_port(&self) -> Result<(String, u16)> {
         const NI_MAXHOST: usize = 1025;
         const NI_MAXSERV: usize = 32;
 
@@ -178,7 +196,7 @@ impl Address {
                 ip_buf.len() as _,
                 port_buf.as_mut_ptr() as _,
                 port_buf.len() as _,
-                AI_NUMERICHOST | AI_NUMERICSERV,
+                NI_NUMERICHOST | NI_NUMERICSERV,
             )
         } {
             0 => {}
@@ -188,30 +206,40 @@ impl Address {
                 ))));
             }
         }
-        let port: u16 = String::from_utf8_lossy(&port_buf).into_owned().parse()?;
+        let port: u16 = CStr::from_bytes_until_nul(&port_buf)?.to_str()?.parse()?;
+        let ip = CStr::from_bytes_until_nul(&ip_buf)?.to_str()?.to_owned();
 
-        Ok((String::from_utf8_lossy(&ip_buf).into_owned(), port))
+        Ok((ip, port))
     }
 
-    pub fn ip(&mut self) -> Result<String> {
+    pub fn ip(&self) -> Result<String> {
         let (ip, _) = self.ip_port()?;
         Ok(ip)
     }
 
-    pub fn port(&mut self) -> Result<u16> {
+    pub fn port(&self) -> Result<u16> {
         let (_, port) = self.ip_port()?;
         Ok(port)
     }
 
     pub fn as_ptr(&self) -> *const sockaddr {
-        self as *const Self as *const sockaddr
+        self.addr.as_ptr()
+    }
+}
+
+impl Display for Address {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self.ip_port().ok() {
+            Some((ip, port)) => write!(f, "{}:{}", ip, port),
+            None => write!(f, "(unresolved address)"),
+        }
     }
 }
 
 impl TryInto<String> for Address {
     type Error = Error;
 
-    fn try_into(mut self) -> Result<String> {
+    fn try_into(self) -> Result<String> {
         let (ip, port) = self.ip_port()?;
         Ok(format!("{}:{}", ip, port))
     }
@@ -246,13 +274,31 @@ impl From<IPv4NUM> for Address {
             },
             sin_zero: [0; 8],
         };
-        Self {
-            addr: unsafe {
-                RawAddr {
-                    storage: *(&ipv4_addr as *const sockaddr_in as *const sockaddr_storage),
-                }
-            },
-            size: size_of::<sockaddr_in>() as _,
-        }
+        let size = size_of::<sockaddr_in>();
+        Self::try_from((&ipv4_addr as *const sockaddr_in as *const sockaddr, size))
+            .expect("sockaddr_in always fits in sockaddr_storage")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ip_port() {
+        let addr = Address::try_from_string("169.254.144.9", "1234").unwrap();
+        assert_eq!(addr.ip_port().unwrap(), ("169.254.144.9".to_string(), 1234));
+        assert_eq!(addr.to_string(), "169.254.144.9:1234");
+
+        let family = unsafe { (*addr.as_ptr()).sa_family };
+        assert_eq!(family as i32, AF_INET);
+        assert!(TryInto::<IPv4NUM>::try_into(&addr).unwrap() == IPv4NUM(0xa9fe9009));
+    }
+
+    #[test]
+    fn test_invalid() {
+        assert!(Address::try_from_string("not an ip", "80").is_err());
+        assert!(Address::try_from_string("10.0.0.1", "http").is_err());
+        assert!(Address::try_from_string("10.0.0.1\0", "80").is_err());
     }
 }
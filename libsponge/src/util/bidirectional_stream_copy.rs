@@ -0,0 +1,271 @@
+use crate::{
+    ByteStream, Direction, EventAction, EventLoop, EventResult, NakedFileDescriptor, Socket,
+};
+
+use anyhow::{Error, Result};
+use libc::{SHUT_WR, STDIN_FILENO, STDOUT_FILENO};
+
+use std::sync::{Arc, Mutex};
+
+const MAX_COPY_LENGTH: usize = 65536;
+const BUFFER_SIZE: usize = 1048576;
+const POLL_TIMEOUT_MS: u64 = 1000;
+
+struct StreamCopyState<A: Default + Clone, P> {
+    socket: Socket<A, P>,
+    input: NakedFileDescriptor,
+    output: NakedFileDescriptor,
+    outbound: ByteStream,
+    inbound: ByteStream,
+    outbound_shutdown: bool,
+    inbound_shutdown: bool,
+    error: Option<Error>,
+}
+
+impl<A: Default + Clone, P> StreamCopyState<A, P> {
+    // rule 1: read from stdin into outbound buffer
+    fn read_input(&mut self) -> Result<()> {
+        let limit = MAX_COPY_LENGTH.min(self.outbound.remaining_capacity());
+        let data = self.input.read(Some(limit))?;
+        self.outbound.write(&data);
+        if self.input.eof() {
+            self.outbound.end_input();
+        }
+        Ok(())
+    }
+
+    fn read_input_interest(&self) -> bool {
+        !self.outbound.error()
+            && !self.outbound.input_ended()
+            && self.outbound.remaining_capacity() > 0
+    }
+
+    fn read_input_cancel(&mut self) {
+        self.outbound.end_input();
+    }
+
+    // rule 2: write outbound buffer into socket, half-closing it once stdin is done
+    fn write_socket(&mut self) -> Result<()> {
+        if !self.outbound.buffer_empty() {
+            let buffer = self
+                .outbound
+                .peek_out(MAX_COPY_LENGTH.min(self.outbound.buffer_size()));
+            let bytes_written = self.socket.write(buffer.as_slice(), false)?;
+            self.outbound.pop_output(bytes_written);
+        }
+
+        if self.outbound.eof() {
+            self.socket.shutdown(SHUT_WR)?;
+            self.outbound_shutdown = true;
+        }
+        Ok(())
+    }
+
+    fn write_socket_interest(&self) -> bool {
+        !self.outbound.error()
+            && (!self.outbound.buffer_empty() || (self.outbound.eof() && !self.outbound_shutdown))
+    }
+
+    fn write_socket_cancel(&mut self) {
+        self.outbound.set_error();
+    }
+
+    // rule 3: read from socket into inbound buffer
+    fn read_socket(&mut self) -> Result<()> {
+        let limit = MAX_COPY_LENGTH.min(self.inbound.remaining_capacity());
+        let data = self.socket.read(Some(limit))?;
+        self.inbound.write(&data);
+        if self.socket.eof() {
+            self.inbound.end_input();
+        }
+        Ok(())
+    }
+
+    fn read_socket_interest(&self) -> bool {
+        !self.inbound.error()
+            && !self.inbound.input_ended()
+            && self.inbound.remaining_capacity() > 0
+    }
+
+    fn read_socket_cancel(&mut self) {
+        self.inbound.end_input();
+    }
+
+    // rule 4: write inbound buffer into stdout, closing it once the socket is done
+    fn write_output(&mut self) -> Result<()> {
+        if !self.inbound.buffer_empty() {
+            let buffer = self
+                .inbound
+                .peek_out(MAX_COPY_LENGTH.min(self.inbound.buffer_size()));
+            let bytes_written = self.output.write(buffer.as_slice(), false)?;
+            self.inbound.pop_output(bytes_written);
+        }
+
+        if self.inbound.eof() {
+            self.output.close()?;
+            self.inbound_shutdown = true;
+        }
+        Ok(())
+    }
+
+    fn write_output_interest(&self) -> bool {
+        !self.inbound.error()
+            && (!self.inbound.buffer_empty() || (self.inbound.eof() && !self.inbound_shutdown))
+    }
+
+    fn write_output_cancel(&mut self) {
+        self.inbound.set_error();
+    }
+}
+
+type StateT<A, P> = Arc<Mutex<StreamCopyState<A, P>>>;
+
+fn add_rule<A, P>(
+    event_loop: &mut EventLoop,
+    state: &StateT<A, P>,
+    fd: NakedFileDescriptor,
+    direction: Direction,
+    callback: fn(&mut StreamCopyState<A, P>) -> Result<()>,
+    interest: fn(&StreamCopyState<A, P>) -> bool,
+    cancel: fn(&mut StreamCopyState<A, P>),
+) where
+    A: Default + Clone + Send + 'static,
+    P: Send + 'static,
+{
+    let (callback_state, interest_state, cancel_state) =
+        (state.clone(), state.clone(), state.clone());
+    event_loop.add_rule(
+        fd,
+        direction,
+        move || {
+            let mut state = callback_state.lock().unwrap();
+            match callback(&mut state) {
+                Ok(()) => EventAction::Continue,
+                Err(err) => {
+                    state.error.get_or_insert(err);
+                    EventAction::Exit
+                }
+            }
+        },
+        move || interest(&interest_state.lock().unwrap()),
+        move || cancel(&mut cancel_state.lock().unwrap()),
+    );
+}
+
+// copy stdin to the socket and the socket to stdout until both directions are finished
+pub fn bidirectional_stream_copy<A, P>(socket: &Socket<A, P>) -> Result<()>
+where
+    A: Default + Clone + Send + 'static,
+    P: Send + 'static,
+{
+    copy_streams(
+        socket,
+        NakedFileDescriptor::from(STDIN_FILENO),
+        NakedFileDescriptor::from(STDOUT_FILENO),
+    )
+}
+
+fn copy_streams<A, P>(
+    socket: &Socket<A, P>,
+    input: NakedFileDescriptor,
+    output: NakedFileDescriptor,
+) -> Result<()>
+where
+    A: Default + Clone + Send + 'static,
+    P: Send + 'static,
+{
+    let socket_fd = NakedFileDescriptor::from(socket);
+    let (input_fd, output_fd) = (
+        NakedFileDescriptor::from(&input),
+        NakedFileDescriptor::from(&output),
+    );
+
+    let state = Arc::new(Mutex::new(StreamCopyState {
+        socket: Socket::<A, P>::from(NakedFileDescriptor::from(&socket_fd)),
+        input,
+        output,
+        outbound: ByteStream::new(BUFFER_SIZE),
+        inbound: ByteStream::new(BUFFER_SIZE),
+        outbound_shutdown: false,
+        inbound_shutdown: false,
+        error: None,
+    }));
+
+    let mut event_loop = EventLoop::new();
+    add_rule(
+        &mut event_loop,
+        &state,
+        input_fd,
+        Direction::In,
+        StreamCopyState::read_input,
+        StreamCopyState::read_input_interest,
+        StreamCopyState::read_input_cancel,
+    );
+    add_rule(
+        &mut event_loop,
+        &state,
+        (&socket_fd).into(),
+        Direction::Out,
+        StreamCopyState::write_socket,
+        StreamCopyState::write_socket_interest,
+        StreamCopyState::write_socket_cancel,
+    );
+    add_rule(
+        &mut event_loop,
+        &state,
+        socket_fd,
+        Direction::In,
+        StreamCopyState::read_socket,
+        StreamCopyState::read_socket_interest,
+        StreamCopyState::read_socket_cancel,
+    );
+    add_rule(
+        &mut event_loop,
+        &state,
+        output_fd,
+        Direction::Out,
+        StreamCopyState::write_output,
+        StreamCopyState::write_output_interest,
+        StreamCopyState::write_output_cancel,
+    );
+
+    while event_loop.wait_next_event(POLL_TIMEOUT_MS.into())? != EventResult::Exit {}
+
+    match state.lock().unwrap().error.take() {
+        Some(err) => Err(err),
+        None => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{LSSocket, NoneAdapter};
+
+    fn read_to_end(socket: &mut LSSocket<NoneAdapter>) -> Vec<u8> {
+        let mut data = Vec::new();
+        while !socket.eof() {
+            data.extend(socket.read(None).unwrap());
+        }
+        data
+    }
+
+    #[test]
+    fn test_copy_both_directions() {
+        let (socket, mut peer) = LSSocket::<NoneAdapter>::try_pair().unwrap();
+        let (mut stdin, input) = LSSocket::<NoneAdapter>::try_pair().unwrap();
+        let (output, mut stdout) = LSSocket::<NoneAdapter>::try_pair().unwrap();
+
+        stdin.write("ping", true).unwrap();
+        stdin.close().unwrap();
+        peer.write("pong", true).unwrap();
+        peer.shutdown(SHUT_WR).unwrap();
+
+        copy_streams(&socket, (&input).into(), (&output).into()).unwrap();
+
+        // stdin's eof is passed on as a half-close, the peer's as a closed stdout
+        assert_eq!(read_to_end(&mut peer), b"ping");
+        assert_eq!(read_to_end(&mut stdout), b"pong");
+        assert!(output.closed());
+    }
+}
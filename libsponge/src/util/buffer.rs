@@ -101,7 +101,7 @@ impl Buffer {
 
     #[inline(always)]
     pub fn take(&mut self) -> Self {
-        std::mem::replace(self, Self::default())
+        std::mem::take(self)
     }
 }
 
@@ -141,11 +141,10 @@ impl From<Vec<Vec<u8>>> for BufferList {
     #[inline(always)]
     fn from(v: Vec<Vec<u8>>) -> Self {
         BufferList {
-            buffers: VecDeque::from(
-                v.into_iter()
-                    .map(Buffer::from)
-                    .collect::<VecDeque<Buffer>>(),
-            ),
+            buffers: v
+                .into_iter()
+                .map(Buffer::from)
+                .collect::<VecDeque<Buffer>>(),
         }
     }
 }
@@ -177,10 +176,10 @@ impl TryInto<Buffer> for &BufferList {
     }
 }
 
-impl Into<Vec<u8>> for &BufferList {
-    fn into(self) -> Vec<u8> {
-        let size = self.len();
-        self.iter().map(|buf| buf.as_ref().to_vec()).fold(
+impl From<&BufferList> for Vec<u8> {
+    fn from(val: &BufferList) -> Self {
+        let size = val.len();
+        val.iter().map(|buf| buf.as_ref().to_vec()).fold(
             Vec::with_capacity(size),
             |mut acc, buf| {
                 acc.extend(buf);
@@ -196,8 +195,16 @@ impl BufferList {
         self.iter().map(|buf| buf.len()).sum()
     }
 
+    #[inline(always)]
+    pub fn is_empty(&self) -> bool {
+        self.iter().all(|buf| buf.is_empty())
+    }
+
     pub fn try_remove_prefix(&mut self, mut n: usize) -> Result<Vec<u8>, BufferError> {
         let mut vec = Vec::with_capacity(n);
+        if n == 0 {
+            return Ok(vec);
+        }
         while let Some(buffer) = self.buffers.front_mut() {
             let mut sub = n.min(buffer.len());
             n -= sub;
@@ -215,6 +222,9 @@ impl BufferList {
 
     pub fn remove_prefix(&mut self, mut n: usize) -> Vec<u8> {
         let mut vec = Vec::with_capacity(n);
+        if n == 0 {
+            return vec;
+        }
         while let Some(buffer) = self.buffers.front_mut() {
             let mut sub = n.min(buffer.len());
             n -= sub;
@@ -238,13 +248,9 @@ impl BufferList {
         self.buffers.iter().map(|buf| buf.as_ref())
     }
 
-    fn iter_mut(&mut self) -> impl Iterator<Item = &mut [u8]> {
-        self.buffers.iter_mut().map(|buf| buf.as_mut())
-    }
-
     #[inline(always)]
     pub fn take(&mut self) -> Self {
-        std::mem::replace(self, Self::default())
+        std::mem::take(self)
     }
 }
 
@@ -272,7 +278,7 @@ impl<'a> From<&'a [u8]> for BufferViewList<'a> {
 impl<'a> From<&'a BufferList> for BufferViewList<'a> {
     fn from(buffers: &'a BufferList) -> Self {
         BufferViewList {
-            views: buffers.iter().map(|buf| buf.as_ref()).collect(),
+            views: buffers.iter().collect(),
         }
     }
 }
@@ -287,6 +293,9 @@ impl<'a> From<&'a str> for BufferViewList<'a> {
 
 impl<'a> BufferViewList<'a> {
     pub fn try_remove_prefix(&mut self, mut n: usize) -> Result<(), BufferError> {
+        if n == 0 {
+            return Ok(());
+        }
         while let Some(buffer) = self.views.front_mut() {
             let sz = buffer.len();
             let mut drop = false;
@@ -345,3 +354,43 @@ impl<'a> BufferViewList<'a> {
 //     }
 //     return ret;
 // }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_buffer_list_remove_prefix() {
+        let mut list = BufferList::default();
+        assert!(list.is_empty());
+        assert_eq!(list.remove_prefix(0), b"");
+        assert_eq!(list.try_remove_prefix(0).unwrap(), b"");
+
+        let mut list = BufferList::from(vec![b"abc".to_vec(), b"def".to_vec()]);
+        assert_eq!(list.remove_prefix(0), b"");
+        assert_eq!(list.remove_prefix(4), b"abcd");
+        assert_eq!(list.len(), 2);
+        assert!(matches!(
+            list.try_remove_prefix(3),
+            Err(BufferError::IndexOutOfBounds)
+        ));
+
+        let mut list = BufferList::from(vec![b"ab".to_vec(), b"cd".to_vec()]);
+        assert_eq!(list.try_remove_prefix(2).unwrap(), b"ab");
+        assert_eq!(Vec::<u8>::from(&list), b"cd");
+        list.remove_prefix(2);
+        assert!(list.is_empty());
+    }
+
+    #[test]
+    fn test_buffer_view_list_remove_prefix() {
+        let mut views = BufferViewList::from("abc");
+        assert!(views.try_remove_prefix(0).is_ok());
+        assert!(views.try_remove_prefix(1).is_ok());
+        assert_eq!(views.len(), 2);
+
+        let mut views = BufferViewList::from("");
+        assert!(views.try_remove_prefix(0).is_ok());
+        assert!(views.try_remove_prefix(1).is_err());
+    }
+}
@@ -3,21 +3,21 @@ use crate::{Milliseconds, NakedFileDescriptor, TaggedError, system_call};
 use libc::{POLLERR, POLLHUP, POLLIN, POLLNVAL, POLLOUT, nfds_t, poll, pollfd};
 use thiserror::Error;
 
-use std::{collections::VecDeque, os::fd::RawFd};
-
-enum Direction {
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
     In,
     Out,
 }
 
-enum EventResult {
+#[derive(Debug, PartialEq, Eq)]
+pub enum EventResult {
     Success,
     Timeout,
     Exit,
 }
 
 #[derive(Error, Debug)]
-enum EventLoopError {
+pub enum EventLoopError {
     #[error("IO error on file descriptor")]
     IoError,
     #[error("Busy wait detected")]
@@ -34,55 +34,38 @@ impl From<TaggedError> for EventLoopError {
     }
 }
 
-enum EventAction {
+pub enum EventAction {
     Continue,
     Remove,
     Exit,
 }
 
-pub trait EventHandler: Send {
-    fn on_event(&mut self, fd: RawFd, direction: &Direction) -> EventAction;
-    fn serv_cnt(&self) -> usize;
-}
+pub type CallbackT = Box<dyn FnMut() -> EventAction + Send>;
+pub type InterestT = Box<dyn Fn() -> bool + Send>;
+pub type CancelT = Box<dyn FnMut() + Send>;
 
-pub struct EventRule {
+struct EventRule {
     fd: NakedFileDescriptor,
     direction: Direction,
-    handler: Box<dyn EventHandler>,
-    serv_cnt: usize,
+    callback: CallbackT,
+    interest: InterestT,
+    cancel: CancelT,
 }
 
 impl EventRule {
-    pub fn new(
-        fd: NakedFileDescriptor,
-        direction: Direction,
-        handler: Box<dyn EventHandler>,
-    ) -> Self {
-        EventRule {
-            fd,
-            direction,
-            handler,
-            serv_cnt: 0,
-        }
-    }
-
-    pub fn interest(&self) -> bool {
-        !self.fd.eof() && !self.fd.closed()
+    fn defunct(&self) -> bool {
+        let eof = match self.direction {
+            Direction::In => self.fd.eof(),
+            Direction::Out => false,
+        };
+        eof || self.fd.closed()
     }
 
-    pub fn callback(&mut self) {
-        match self.handler.on_event(self.fd.fd(), &self.direction) {
-            EventAction::Continue => {}
-            EventAction::Remove => {
-                todo!();
-            }
-            EventAction::Exit => {
-                todo!();
-            }
-        }
+    fn interest(&self) -> bool {
+        !self.defunct() && (self.interest)()
     }
 
-    pub fn serv_cnt(&self) -> usize {
+    fn serv_cnt(&self) -> usize {
         match self.direction {
             Direction::In => self.fd.read_count(),
             Direction::Out => self.fd.write_count(),
@@ -91,26 +74,43 @@ impl EventRule {
 }
 
 pub struct EventLoop {
-    rules: VecDeque<EventRule>,
-    to_remove: Vec<usize>,
+    rules: Vec<EventRule>,
     should_exit: bool,
 }
 
+impl Default for EventLoop {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl EventLoop {
     pub fn new() -> Self {
         Self {
-            rules: VecDeque::new(),
-            to_remove: Vec::new(),
+            rules: Vec::new(),
             should_exit: false,
         }
     }
 
-    pub fn add_rule(&mut self, rule: EventRule) {
-        self.rules.push_back(rule);
+    pub fn add_rule(
+        &mut self,
+        fd: NakedFileDescriptor,
+        direction: Direction,
+        callback: impl FnMut() -> EventAction + Send + 'static,
+        interest: impl Fn() -> bool + Send + 'static,
+        cancel: impl FnMut() + Send + 'static,
+    ) {
+        self.rules.push(EventRule {
+            fd,
+            direction,
+            callback: Box::new(callback),
+            interest: Box::new(interest),
+            cancel: Box::new(cancel),
+        });
     }
 
     pub fn run(&mut self, time_out: Milliseconds) -> Result<(), EventLoopError> {
-        while !self.should_exit {
+        loop {
             match self.wait_next_event(time_out)? {
                 EventResult::Exit => break,
                 EventResult::Timeout => continue,
@@ -124,15 +124,21 @@ impl EventLoop {
         &mut self,
         timeout_ms: Milliseconds,
     ) -> Result<EventResult, EventLoopError> {
-        self.cleanup_rules();
-
-        if self.rules.is_empty() {
+        if self.should_exit {
             return Ok(EventResult::Exit);
         }
 
-        let (pollfds, something_to_poll) = self.build_pollfds();
+        // rules whose fd reached eof or was closed can never fire again
+        self.rules.retain_mut(|rule| {
+            if rule.defunct() {
+                (rule.cancel)();
+                return false;
+            }
+            true
+        });
 
-        if !something_to_poll {
+        let pollfds = self.build_pollfds();
+        if pollfds.iter().all(|pollfd| pollfd.events == 0) {
             return Ok(EventResult::Exit);
         }
 
@@ -157,72 +163,178 @@ impl EventLoop {
         }
 
         self.process_poll_results(&pollfds)?;
-        self.cleanup_rules();
 
+        if self.should_exit {
+            return Ok(EventResult::Exit);
+        }
         Ok(EventResult::Success)
     }
 
-    fn build_pollfds(&self) -> (Vec<pollfd>, bool) {
-        let mut pollfds = Vec::with_capacity(self.rules.len());
-        let mut something_to_poll = false;
-
-        for rule in &self.rules {
-            let events = if rule.interest() {
-                something_to_poll = true;
-                match rule.direction {
-                    Direction::In => POLLIN as i16,
-                    Direction::Out => POLLOUT as i16,
-                }
-            } else {
-                0
-            };
-
-            pollfds.push(pollfd {
+    fn build_pollfds(&self) -> Vec<pollfd> {
+        self.rules
+            .iter()
+            .map(|rule| pollfd {
                 fd: rule.fd.fd(),
-                events,
+                // uninterested rules are still polled so a hangup can be noticed
+                events: match (rule.interest(), &rule.direction) {
+                    (false, _) => 0,
+                    (true, Direction::In) => POLLIN,
+                    (true, Direction::Out) => POLLOUT,
+                },
                 revents: 0,
-            });
-        }
-
-        (pollfds, something_to_poll)
+            })
+            .collect()
     }
 
     fn process_poll_results(&mut self, pollfds: &[pollfd]) -> Result<(), EventLoopError> {
-        for (idx, pollfd) in pollfds.iter().enumerate() {
-            if pollfd.revents & (POLLERR as i16 | POLLNVAL as i16) != 0 {
-                return Err(EventLoopError::IoError);
+        let mut pollfds = pollfds.iter();
+        let mut result = Ok(());
+        let mut should_exit = false;
+
+        self.rules.retain_mut(|rule| {
+            let pollfd = pollfds.next().expect("one pollfd per rule");
+            if result.is_err() || should_exit {
+                return true;
             }
 
-            let rule = &mut self.rules[idx];
+            let poll_error = pollfd.revents & (POLLERR | POLLNVAL) != 0;
             let poll_ready = pollfd.revents & pollfd.events != 0;
-            let poll_hup = pollfd.revents & (POLLHUP as i16) != 0;
+            let poll_hup = pollfd.revents & POLLHUP != 0;
 
-            if poll_hup && pollfd.events != 0 && !poll_ready {
-                self.to_remove.push(idx);
-                continue;
+            // the fd is defunct if it errored or its only condition was a hangup
+            if poll_error || (poll_hup && pollfd.events != 0 && !poll_ready) {
+                (rule.cancel)();
+                return false;
             }
 
-            if poll_ready {
-                let count_before = rule.serv_cnt();
-                rule.callback();
-                if count_before == rule.serv_cnt() && rule.interest() {
-                    return Err(EventLoopError::BusyWait);
+            if !poll_ready {
+                return true;
+            }
+
+            let count_before = rule.serv_cnt();
+            match (rule.callback)() {
+                EventAction::Continue => {}
+                EventAction::Remove => return false,
+                EventAction::Exit => {
+                    should_exit = true;
+                    return true;
                 }
             }
-        }
-        Ok(())
+            if count_before == rule.serv_cnt() && rule.interest() {
+                result = Err(EventLoopError::BusyWait);
+            }
+            true
+        });
+
+        self.should_exit |= should_exit;
+        result
     }
+}
 
-    fn cleanup_rules(&mut self) {
-        if self.to_remove.is_empty() {
-            return;
-        }
-        self.to_remove.sort_unstable_by(|a, b| b.cmp(a));
-        for &idx in &self.to_remove {
-            if idx < self.rules.len() {
-                self.rules.remove(idx);
-            }
-        }
-        self.to_remove.clear();
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{LSSocket, NoneAdapter};
+
+    use std::sync::{
+        Arc,
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+    };
+
+    fn pair() -> (LSSocket<NoneAdapter>, LSSocket<NoneAdapter>) {
+        LSSocket::try_pair().unwrap()
+    }
+
+    #[test]
+    fn test_remove_and_exit() {
+        let (mut a, b) = pair();
+        a.write("ping", true).unwrap();
+
+        let mut event_loop = EventLoop::new();
+        let mut reader = LSSocket::<NoneAdapter>::from(NakedFileDescriptor::from(&b));
+        event_loop.add_rule(
+            (&b).into(),
+            Direction::In,
+            move || {
+                assert_eq!(reader.read(None).unwrap(), b"ping");
+                EventAction::Remove
+            },
+            || true,
+            || panic!("a removed rule is not cancelled"),
+        );
+        assert_eq!(
+            event_loop.wait_next_event(10.into()).unwrap(),
+            EventResult::Success
+        );
+        // no rules left
+        assert_eq!(
+            event_loop.wait_next_event(10.into()).unwrap(),
+            EventResult::Exit
+        );
+
+        event_loop.add_rule(
+            (&a).into(),
+            Direction::Out,
+            || EventAction::Exit,
+            || true,
+            || {},
+        );
+        assert_eq!(
+            event_loop.wait_next_event(10.into()).unwrap(),
+            EventResult::Exit
+        );
+        assert_eq!(
+            event_loop.wait_next_event(10.into()).unwrap(),
+            EventResult::Exit
+        );
+    }
+
+    #[test]
+    fn test_interest_and_cancel() {
+        let (a, b) = pair();
+        let interested = Arc::new(AtomicBool::new(false));
+        let served = Arc::new(AtomicUsize::new(0));
+        let cancelled = Arc::new(AtomicBool::new(false));
+
+        let mut event_loop = EventLoop::new();
+        let mut reader = LSSocket::<NoneAdapter>::from(NakedFileDescriptor::from(&b));
+        let (i, s, c) = (interested.clone(), served.clone(), cancelled.clone());
+        event_loop.add_rule(
+            (&b).into(),
+            Direction::In,
+            move || {
+                reader.read(None).unwrap();
+                s.fetch_add(1, Ordering::SeqCst);
+                EventAction::Continue
+            },
+            move || i.load(Ordering::SeqCst),
+            move || c.store(true, Ordering::SeqCst),
+        );
+
+        // nothing to poll while the rule is not interested
+        assert_eq!(
+            event_loop.wait_next_event(10.into()).unwrap(),
+            EventResult::Exit
+        );
+
+        interested.store(true, Ordering::SeqCst);
+        assert_eq!(
+            event_loop.wait_next_event(10.into()).unwrap(),
+            EventResult::Timeout
+        );
+
+        // closing the peer makes the read hit eof, after which the rule is cancelled
+        a.close().unwrap();
+        assert_eq!(
+            event_loop.wait_next_event(10.into()).unwrap(),
+            EventResult::Success
+        );
+        assert_eq!(served.load(Ordering::SeqCst), 1);
+        assert!(!cancelled.load(Ordering::SeqCst));
+        assert_eq!(
+            event_loop.wait_next_event(10.into()).unwrap(),
+            EventResult::Exit
+        );
+        assert!(cancelled.load(Ordering::SeqCst));
     }
 }
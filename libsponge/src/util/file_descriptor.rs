@@ -7,7 +7,6 @@ use std::{
     marker::PhantomData,
     os::fd::RawFd,
     sync::{Arc, Mutex},
-    usize,
 };
 
 const BUFFER_SIZE: usize = 1024 * 1024;
@@ -57,6 +56,7 @@ impl Drop for FDWrapper {
 #[derive(Clone)]
 pub struct FileDescriptor<A: Default + Clone, T, P> {
     internal_fd: Arc<Mutex<FDWrapper>>,
+    #[allow(dead_code)]
     adapter: A,
 
     _type: PhantomData<T>,
@@ -89,22 +89,21 @@ impl<A: Default + Clone, T, P> FileDescriptor<A, T, P> {
         self.internal_fd.lock().unwrap().write_count
     }
 
-    #[allow(unused)]
     pub fn set_blocking(&mut self, blocking: bool) -> Result<(), TaggedError> {
-        let mut fd = self.internal_fd.lock().unwrap().fd;
+        let fd = self.internal_fd.lock().unwrap().fd;
         let mut flags = system_call("fcntl", || unsafe { libc::fcntl(fd, libc::F_GETFL) })?;
         if blocking {
             flags ^= flags & libc::O_NONBLOCK;
         } else {
             flags |= libc::O_NONBLOCK;
         }
-        system_call("fcntl", || unsafe { libc::fcntl(fd, flags) })?;
+        system_call("fcntl", || unsafe { libc::fcntl(fd, libc::F_SETFL, flags) })?;
         Ok(())
     }
 
     pub fn read(&mut self, limit: Option<usize>) -> Result<Vec<u8>, TaggedError> {
         let lmt = limit.unwrap_or(usize::MAX);
-        let mut ret = Vec::with_capacity(lmt);
+        let mut ret = Vec::with_capacity(BUFFER_SIZE.min(lmt));
         self.read_into_vec(&mut ret, lmt)?;
         Ok(ret)
     }
@@ -117,7 +116,7 @@ impl<A: Default + Clone, T, P> FileDescriptor<A, T, P> {
         let bytes_read = system_call("read", || unsafe {
             libc::read(fdw.fd, buf.as_mut_ptr() as *mut c_void, size_to_read as _)
         })?;
-        if limit > 0 && fdw.read_count == 0 {
+        if limit > 0 && bytes_read == 0 {
             fdw.eof = true;
         }
 
@@ -128,7 +127,7 @@ impl<A: Default + Clone, T, P> FileDescriptor<A, T, P> {
             ));
         }
 
-        buf.resize(size_to_read, 0);
+        buf.resize(bytes_read as _, 0);
         fdw.read_count += 1;
         Ok(())
     }
@@ -151,7 +150,7 @@ impl<A: Default + Clone, T, P> FileDescriptor<A, T, P> {
                 )
             })?;
 
-            if bytes_written == 0 && buf.is_empty() {
+            if bytes_written == 0 && !buf.is_empty() {
                 return Err(Error::new(TaggedError::unix(
                     "write returned 0 given non-empty input buffer",
                 )));
@@ -195,9 +194,9 @@ impl<A: Default + Clone, T, P> From<RawFd> for FileDescriptor<A, T, P> {
     }
 }
 
-impl<A: Default + Clone, T, P> Into<RawFd> for FileDescriptor<A, T, P> {
-    fn into(self) -> RawFd {
-        self.internal_fd.lock().unwrap().fd
+impl<A: Default + Clone, T, P> From<FileDescriptor<A, T, P>> for RawFd {
+    fn from(val: FileDescriptor<A, T, P>) -> Self {
+        val.internal_fd.lock().unwrap().fd
     }
 }
 
@@ -207,13 +206,24 @@ pub struct NakedFD;
 pub struct NoneProtocol;
 pub type NakedFileDescriptor = FileDescriptor<NoneAdapter, NakedFD, NoneProtocol>;
 
-impl<A: Default + Clone, T, P> Into<NakedFileDescriptor> for &FileDescriptor<A, T, P> {
-    fn into(self) -> NakedFileDescriptor {
+impl<A: Default + Clone, T, P> From<&FileDescriptor<A, T, P>> for NakedFileDescriptor {
+    fn from(val: &FileDescriptor<A, T, P>) -> Self {
         NakedFileDescriptor {
-            internal_fd: self.internal_fd.clone(),
-            adapter: NoneAdapter::default(),
+            internal_fd: val.internal_fd.clone(),
+            adapter: NoneAdapter,
             _type: PhantomData::<NakedFD>,
             _protocol: PhantomData::<NoneProtocol>,
         }
     }
 }
+
+impl NakedFileDescriptor {
+    pub(crate) fn into_typed<A: Default + Clone, T, P>(self) -> FileDescriptor<A, T, P> {
+        FileDescriptor {
+            internal_fd: self.internal_fd,
+            adapter: A::default(),
+            _type: PhantomData::<T>,
+            _protocol: PhantomData::<P>,
+        }
+    }
+}
@@ -20,6 +20,8 @@ pub enum ParseError {
     Unsupported,
     #[error("Payload size mismatch")]
     PayloadSizeMismatch,
+    #[error("TCP option length is malformed")]
+    BadOptionLength,
 }
 
 impl ParseError {
@@ -36,6 +38,7 @@ impl ParseError {
             }
             ParseError::Unsupported => "Packet uses unsupported features".to_string(),
             ParseError::PayloadSizeMismatch => "Payload size mismatch".to_string(),
+            ParseError::BadOptionLength => "TCP option length is malformed".to_string(),
         }
     }
 }
@@ -89,14 +92,17 @@ impl NetParser {
         self.check_size(len);
 
         let mut ret = T::from(0);
-        if self.buffer.is_empty() {
+        if self.is_err() {
             return ret;
         }
 
         for i in 0..len {
-            ret <<= 8;
+            if i > 0 {
+                ret <<= 8;
+            }
             ret += self.buffer.at(i).into();
         }
+        self.remove_prefix(len);
         ret
     }
 
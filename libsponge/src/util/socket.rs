@@ -5,7 +5,7 @@ use libc::{
     AF_INET, AF_UNIX, MSG_TRUNC, SHUT_RD, SHUT_RDWR, SHUT_WR, SO_DOMAIN, SO_REUSEADDR, SO_TYPE,
     SOCK_DGRAM, SOCK_STREAM, SOL_SOCKET, accept, bind, c_int, c_void, connect, getpeername,
     getsockname, getsockopt, iovec, listen, msghdr, recvfrom, sendmsg, setsockopt, shutdown,
-    sockaddr, socket, socklen_t,
+    sockaddr, socket, socketpair, socklen_t,
 };
 use thiserror::Error;
 
@@ -30,7 +30,7 @@ pub struct SocketT;
 pub type Socket<A, P> = FileDescriptor<A, SocketT, P>;
 impl<A: Default + Clone, P> From<NakedFileDescriptor> for Socket<A, P> {
     fn from(fd: NakedFileDescriptor) -> Self {
-        Socket::from(Into::<RawFd>::into(fd))
+        fd.into_typed()
     }
 }
 
@@ -40,9 +40,9 @@ impl<A: Default + Clone, P> Socket<A, P> {
         F: FnOnce(i32, *mut sockaddr, *mut socklen_t) -> i32,
     {
         let mut addr = RawAddr::default();
-        let size = std::mem::size_of::<RawAddr>();
-        system_call(func_name, || func(self.fd(), addr.as_mut_ptr(), size as _))?;
-        Ok(Address::new(size as _, addr.storage))
+        let mut size = size_of::<RawAddr>() as socklen_t;
+        system_call(func_name, || func(self.fd(), addr.as_mut_ptr(), &mut size))?;
+        Ok(Address::new(size, addr.storage))
     }
 
     fn try_build(fd: Option<NakedFileDescriptor>, domain: i32, ty: i32) -> Result<Socket<A, P>> {
@@ -185,6 +185,13 @@ pub struct RcvdDatagram {
     pub payload: Vec<u8>,
 }
 
+impl RcvdDatagram {
+    #[inline(always)]
+    pub fn src_addr(&self) -> &Address {
+        &self.src_addr
+    }
+}
+
 fn send_helper(
     fd: RawFd,
     des_addr: *mut sockaddr,
@@ -214,7 +221,7 @@ fn send_helper(
 }
 
 impl<A: Default + Clone> UDPSocket<A> {
-    fn try_from_fd(fd: NakedFileDescriptor) -> Result<Self> {
+    pub fn try_from_fd(fd: NakedFileDescriptor) -> Result<Self> {
         Self::try_build(Some(fd), AF_INET, SOCK_DGRAM)
     }
 
@@ -236,7 +243,7 @@ impl<A: Default + Clone> UDPSocket<A> {
         let mtu = mtu.unwrap_or(65536);
         let mut addr = RawAddr::default();
         datagram.payload.resize(mtu, 0);
-        let fromlen = size_of::<Address>();
+        let mut fromlen = size_of::<RawAddr>() as socklen_t;
         let recv_len = system_call("recvfrom", || unsafe {
             recvfrom(
                 self.fd(),
@@ -244,7 +251,7 @@ impl<A: Default + Clone> UDPSocket<A> {
                 mtu as _,
                 MSG_TRUNC,
                 addr.as_mut_ptr(),
-                fromlen as _,
+                &mut fromlen,
             )
         })?;
 
@@ -255,19 +262,24 @@ impl<A: Default + Clone> UDPSocket<A> {
         }
 
         self.register_read();
-        datagram.src_addr = Address::new(fromlen as _, addr.storage);
+        datagram.src_addr = Address::new(fromlen, addr.storage);
         datagram.payload.resize(recv_len as _, 0);
         Ok(())
     }
 
-    pub fn send_to(&mut self, des: &mut Address, payload: &BufferViewList) -> Result<()> {
-        send_helper(self.fd(), des as *mut Address as *mut _, des.size, payload)?;
+    pub fn send_to(&mut self, des: &Address, payload: &BufferViewList) -> Result<()> {
+        send_helper(self.fd(), des.as_ptr() as *mut _, des.size, payload)?;
         self.register_write();
         Ok(())
     }
 
     pub fn send(&mut self, payload: &BufferViewList) -> Result<()> {
-        send_helper(self.fd(), 0 as *mut Address as *mut _, 0, payload)?;
+        send_helper(
+            self.fd(),
+            std::ptr::null_mut::<Address>() as *mut _,
+            0,
+            payload,
+        )?;
         self.register_write();
         Ok(())
     }
@@ -277,4 +289,15 @@ impl<A: Default + Clone> LSSocket<A> {
     fn try_from_fd(fd: NakedFileDescriptor) -> Result<Self> {
         Self::try_build(Some(fd), AF_UNIX, SOCK_STREAM)
     }
+
+    pub fn try_pair() -> Result<(Self, Self)> {
+        let mut fds: [c_int; 2] = [0; 2];
+        system_call("socketpair", || unsafe {
+            socketpair(AF_UNIX, SOCK_STREAM, 0, fds.as_mut_ptr())
+        })?;
+        Ok((
+            Self::try_from_fd(NakedFileDescriptor::from(fds[0]))?,
+            Self::try_from_fd(NakedFileDescriptor::from(fds[1]))?,
+        ))
+    }
 }
@@ -5,9 +5,13 @@ use libc::{
     IFF_NO_PI, IFF_TAP, IFF_TUN, IFNAMSIZ, O_RDWR, TUNSETIFF, c_int, c_void, ifreq, ioctl, open,
 };
 
-use std::{ffi::CString, mem::zeroed, ptr::copy_nonoverlapping};
+use std::{
+    ffi::{CStr, CString},
+    mem::zeroed,
+    ptr::copy_nonoverlapping,
+};
 
-const CLONEDEV: &str = "/dev/net/tun";
+const CLONEDEV: &CStr = c"/dev/net/tun";
 
 pub trait DeviceType: sealed::Sealed {
     const IS_TUN: bool;
@@ -37,9 +41,7 @@ impl DeviceType for Tap {
 fn try_new_tuntap<A: Default + Clone, T: DeviceType, P>(
     dev_name: &str,
 ) -> Result<FileDescriptor<A, T, P>> {
-    let fd = system_call("open", || unsafe {
-        open(CLONEDEV as *const str as *const i8, O_RDWR)
-    })?;
+    let fd = system_call("open", || unsafe { open(CLONEDEV.as_ptr(), O_RDWR) })?;
 
     let this = FileDescriptor::from(fd);
     let mut tun_req: ifreq = unsafe { zeroed() };
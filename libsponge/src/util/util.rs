@@ -1,9 +1,10 @@
 use libc::{c_int, ssize_t};
+use rand::random;
 use thiserror::Error;
 
 use std::{
     io,
-    ops::{AddAssign, Mul, MulAssign, ShlAssign},
+    ops::{AddAssign, Mul, MulAssign, ShlAssign, Sub},
     time::{SystemTime, UNIX_EPOCH},
 };
 
@@ -68,9 +69,9 @@ impl From<u64> for Milliseconds {
     }
 }
 
-impl Into<u64> for Milliseconds {
-    fn into(self) -> u64 {
-        self.0
+impl From<Milliseconds> for u64 {
+    fn from(val: Milliseconds) -> Self {
+        val.0
     }
 }
 
@@ -100,6 +101,14 @@ impl Mul<u64> for Milliseconds {
     }
 }
 
+impl Sub for Milliseconds {
+    type Output = Self;
+
+    fn sub(self, other: Self) -> Self {
+        Self(self.0.saturating_sub(other.0))
+    }
+}
+
 pub fn timestamp_ms() -> Milliseconds {
     (SystemTime::now()
         .duration_since(UNIX_EPOCH)
@@ -108,6 +117,11 @@ pub fn timestamp_ms() -> Milliseconds {
         .into()
 }
 
+// a local port for the sponge sockets, never 0 or privileged
+pub fn random_port() -> u16 {
+    random::<u16>() % 50000 + 1025
+}
+
 #[derive(Debug, Clone, Default)]
 pub struct InternetChecksum {
     sum: u32,
@@ -145,19 +159,20 @@ pub fn hexdump(data: &[u8], indent: usize) -> String {
     for (i, chunk) in data.chunks(16).enumerate() {
         output.push_str(&format!("{}{:08x}: ", indent_str, i * 16));
 
-        for (j, &byte) in chunk.iter().enumerate() {
-            if j == 8 {
+        for j in 0..16 {
+            match chunk.get(j) {
+                Some(byte) => output.push_str(&format!("{:02x}", byte)),
+                None => output.push_str("  "),
+            }
+            if j % 2 == 1 {
+                output.push(' ');
+            }
+            if j == 7 {
                 output.push(' ');
             }
-            output.push_str(&format!("{:02x} ", byte));
-        }
-
-        if chunk.len() < 16 {
-            let spaces = (16 - chunk.len()) * 3 + if chunk.len() <= 8 { 1 } else { 0 };
-            output.push_str(&" ".repeat(spaces));
         }
 
-        output.push_str(" ");
+        output.push(' ');
 
         for &byte in chunk {
             output.push(if (32..127).contains(&byte) {
@@ -180,7 +195,10 @@ mod tests {
     #[test]
     fn test_checksum() {
         let mut checksum = InternetChecksum::new(0);
-        let data = [0x45, 0x00, 0x00, 0x73, 0x00, 0x00, 0x40, 0x00];
+        let data = [
+            0x45, 0x00, 0x00, 0x73, 0x00, 0x00, 0x40, 0x00, 0x40, 0x11, 0x00, 0x00, 0xc0, 0xa8,
+            0x00, 0x01, 0xc0, 0xa8, 0x00, 0xc7,
+        ];
         checksum.add(&data);
         let result = checksum.value();
         assert_eq!(result, 0xb861);
@@ -191,5 +209,13 @@ mod tests {
         let data = b"Hello, World! This is a test.";
         let dump = hexdump(data, 2);
         assert!(dump.contains("4865 6c6c 6f2c 2057  "));
+        // a short last line is padded so the ASCII column lines up
+        let lines: Vec<&str> = dump.lines().collect();
+        assert_eq!(lines[0].find("Hello"), lines[1].find("is is a test."));
+    }
+
+    #[test]
+    fn test_random_port() {
+        assert!((0..1000).map(|_| random_port()).all(|port| port > 1024));
     }
 }